@@ -0,0 +1,4 @@
+-- Track when a notification was acknowledged
+ALTER TABLE notifications ADD COLUMN acknowledged_at TIMESTAMP;
+
+CREATE INDEX idx_notifications_domain ON notifications (domain);
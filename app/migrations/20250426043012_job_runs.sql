@@ -0,0 +1,16 @@
+-- Create a table to store the history of scheduled & manual job runs
+CREATE TABLE job_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    provider TEXT,
+    trigger TEXT NOT NULL,
+    outcome TEXT NOT NULL DEFAULT 'running',
+    error TEXT,
+    added INTEGER NOT NULL DEFAULT 0,
+    deleted INTEGER NOT NULL DEFAULT 0,
+    changed INTEGER NOT NULL DEFAULT 0,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    finished_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_job_runs_kind_provider ON job_runs (kind, provider, started_at);
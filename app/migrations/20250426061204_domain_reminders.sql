@@ -0,0 +1,9 @@
+-- Expiry reminders that have been sent, per domain & expiry date so renewing re-arms them
+CREATE TABLE domain_reminders (
+    domain TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    threshold INTEGER NOT NULL,
+    expiry_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    sent_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (domain, provider, threshold, expiry_at)
+);
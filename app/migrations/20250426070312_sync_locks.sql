@@ -0,0 +1,8 @@
+-- Per provider lease so only one process (server, cli) syncs a provider at a time
+CREATE TABLE sync_locks (
+    provider TEXT PRIMARY KEY NOT NULL,
+    job_run_id INTEGER NOT NULL,
+    holder TEXT NOT NULL,
+    acquired_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
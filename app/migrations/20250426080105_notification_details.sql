@@ -0,0 +1,3 @@
+-- Provider & structured diff of a notification, used by channels that deliver json (webhooks)
+ALTER TABLE notifications ADD COLUMN provider TEXT;
+ALTER TABLE notifications ADD COLUMN diff JSON;
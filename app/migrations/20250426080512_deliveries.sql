@@ -0,0 +1,15 @@
+-- Every attempt to deliver a notification to a channel
+CREATE TABLE deliveries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    channel TEXT NOT NULL,
+    notification_id INTEGER NOT NULL,
+    attempt INTEGER NOT NULL,
+    success BOOLEAN NOT NULL,
+    status INTEGER,
+    error TEXT,
+    duration_ms INTEGER NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX idx_deliveries_notification ON deliveries (notification_id);
+CREATE INDEX idx_deliveries_channel ON deliveries (channel, created_at);
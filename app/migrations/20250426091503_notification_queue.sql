@@ -0,0 +1,10 @@
+-- Notifications a channel holds back for its digest or until its quiet hours end
+CREATE TABLE notification_queue (
+    channel TEXT NOT NULL,
+    notification_id INTEGER NOT NULL REFERENCES notifications (id) ON DELETE CASCADE,
+    release_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    queued_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (channel, notification_id)
+);
+
+CREATE INDEX idx_notification_queue_release ON notification_queue (release_at);
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use figment::{providers::Env, Figment};
+
+use crate::{server::HttpConfig, state::get_config_file, Error};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Probe the health endpoint of the local server, fails unless it responds with 200.
+/// Used by the docker HEALTHCHECK as the image has no curl.
+pub async fn healthcheck(url: Option<String>, ready: bool) -> Result<(), Error> {
+    let path = if ready { "/readyz" } else { "/healthz" };
+
+    let http = Figment::new()
+        .merge(Env::prefixed("DMN_SERVER_"))
+        .merge(get_config_file().unwrap_or_default().focus("server"))
+        .extract::<HttpConfig>()
+        .unwrap_or_default();
+
+    let status = match (url, &http.socket) {
+        (Some(url), _) => probe_url(&format!("{}{}", url.trim_end_matches('/'), path)).await?,
+        #[cfg(unix)]
+        (None, Some(socket)) => probe_socket(&shellexpand::tilde(socket), path).await?,
+        #[cfg(not(unix))]
+        (None, Some(_)) => return Err(Error::msg("Unix sockets are not supported on this platform")),
+        (None, None) => {
+            let scheme = if http.tls_cert.is_some() { "https" } else { "http" };
+            let host = match http.host.as_deref() {
+                None | Some("0.0.0.0") => "127.0.0.1",
+                Some("::") => "[::1]",
+                Some(host) => host,
+            };
+            let port = http.port.unwrap_or(3000);
+
+            probe_url(&format!("{}://{}:{}{}", scheme, host, port, path)).await?
+        }
+    };
+
+    if status == 200 {
+        println!("ok");
+        Ok(())
+    } else {
+        Err(Error::msg(format!("Health check failed with status {}", status)))
+    }
+}
+
+async fn probe_url(url: &str) -> Result<u16, Error> {
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        // the certificate is issued for the public name, not for localhost
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    Ok(client.get(url).send().await?.status().as_u16())
+}
+
+#[cfg(unix)]
+async fn probe_socket(socket: &str, path: &str) -> Result<u16, Error> {
+    use async_std::{io::prelude::*, io::timeout, os::unix::net::UnixStream};
+
+    timeout(TIMEOUT, async {
+        let mut stream = UnixStream::connect(socket).await?;
+        stream
+            .write_all(format!("GET {} HTTP/1.0\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await?;
+
+        // "HTTP/1.0 200 OK"
+        response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| std::io::Error::other("Invalid http response"))
+    })
+    .await
+    .map_err(Error::from)
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Row, Table};
+
+use crate::{
+    models::job_run::{JobRun, JobRunFilter, OUTCOME_FAILURE, OUTCOME_SUCCESS},
+    state::{AppState, AppStateInner},
+    util, Error,
+};
+
+/// Show the most recent job runs
+pub async fn jobs(filter: JobRunFilter, limit: u32) -> Result<(), Error> {
+    let state: AppState = Arc::new(AppStateInner::init(false).await);
+    let runs = JobRun::find_recent(&state, &filter, limit).await?;
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Id", "Job", "Provider", "Trigger", "Outcome", "Started", "Duration", "+ / - / ~", "Error",
+    ]);
+
+    for run in runs {
+        let outcome_cell = match run.outcome.as_str() {
+            OUTCOME_SUCCESS => Cell::new(&run.outcome).fg(Color::Green),
+            OUTCOME_FAILURE => Cell::new(&run.outcome).fg(Color::Red),
+            _ => Cell::new(&run.outcome).fg(Color::Yellow),
+        };
+
+        table.add_row(Row::from(vec![
+            Cell::new(run.id),
+            Cell::new(&run.kind),
+            Cell::new(match &run.provider {
+                Some(provider) => util::color::colorize_provider(provider),
+                None => "-".to_string(),
+            }),
+            Cell::new(&run.trigger),
+            outcome_cell,
+            Cell::new(chrono_humanize::HumanTime::from(run.started_at - Utc::now())),
+            Cell::new(match run.finished_at {
+                Some(finished_at) => format!(
+                    "{:.1}s",
+                    (finished_at - run.started_at).num_milliseconds() as f64 / 1000.0
+                ),
+                None => "-".to_string(),
+            }),
+            Cell::new(format!("{} / {} / {}", run.added, run.deleted, run.changed)),
+            Cell::new(run.error.as_deref().unwrap_or("")),
+        ]));
+    }
+
+    println!("{}", table);
+
+    Ok(())
+}
@@ -3,18 +3,24 @@ use clap::{Parser, Subcommand};
 use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Row, Table};
 use csv::Writer;
 use figment::Figment;
+use notify::NotifyCommands;
 use porkbun::PorkbunCommands;
+use token::TokenCommands;
 use crate::models::domain::Domain;
-use crate::modules::{
-    cloudflare::CloudflareService, whois::whois, DomainService,
-};
+use crate::models::job_run::{JobRunFilter, TRIGGER_CLI};
+use crate::modules::{cloudflare::CloudflareService, whois::whois};
+use crate::server::sync::sync_domains;
 use crate::state::{AppState, AppStateInner};
 use crate::{server, util, Error};
 use std::io::Write;
 use std::process::{Command, Stdio};
 use std::sync::Arc;
 
+mod healthcheck;
+mod jobs;
+mod notify;
 mod porkbun;
+mod token;
 
 #[derive(Parser)]
 #[command(
@@ -52,6 +58,40 @@ enum Commands {
         #[command(subcommand)]
         subcommand: CloudflareCommands,
     },
+    /// Api token related commands
+    Token {
+        #[command(subcommand)]
+        subcommand: TokenCommands,
+    },
+    /// Notification related commands
+    Notify {
+        #[command(subcommand)]
+        subcommand: NotifyCommands,
+    },
+    /// Show the history of sync & background job runs
+    Jobs {
+        /// Only runs of this job (domains, dns, pricing)
+        #[arg(long)]
+        kind: Option<String>,
+        /// Only runs for this provider
+        #[arg(long)]
+        provider: Option<String>,
+        /// Only runs with this outcome (running, success, failure)
+        #[arg(long)]
+        outcome: Option<String>,
+        /// Number of runs to show
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// Check the health of the running server (for docker & kubernetes)
+    Healthcheck {
+        /// Url of the server (default: derived from the [server] config)
+        #[arg(long)]
+        url: Option<String>,
+        /// Check readiness (database & provider credentials) instead of liveness
+        #[arg(long)]
+        ready: bool,
+    },
     /// fzf extension
     Fzf,
     /// Whois related commands
@@ -81,6 +121,9 @@ pub async fn handle_args() -> Result<(), Error> {
             let http = server::start_http(state.clone());
             let cache_size_notifier = state.cache.collect(&state);
             cache_size_notifier.race(http).await;
+
+            state.database.pool.close().await;
+            tracing::info!("Shutdown complete");
         }
         Commands::Ls {
             exact_dates,
@@ -271,6 +314,12 @@ pub async fn handle_args() -> Result<(), Error> {
         Commands::Porkbun { subcommand } => {
             subcommand.handle().await?;
         }
+        Commands::Token { subcommand } => {
+            subcommand.handle().await?;
+        }
+        Commands::Notify { subcommand } => {
+            subcommand.handle().await?;
+        }
         Commands::Cloudflare { subcommand } => {
             let cloudflare = CloudflareService::try_init(&Figment::new())
                 .await
@@ -280,10 +329,26 @@ pub async fn handle_args() -> Result<(), Error> {
             match subcommand {
                 CloudflareCommands::Index => {
                     println!("Indexing Cloudflare domains");
-                    cloudflare.ingest_domains(&state).await?;
+                    sync_domains(&state, "cloudflare", &cloudflare, TRIGGER_CLI).await?;
                 }
             }
         }
+        Commands::Jobs {
+            kind,
+            provider,
+            outcome,
+            limit,
+        } => {
+            let filter = JobRunFilter {
+                kind: kind.clone(),
+                provider: provider.clone(),
+                outcome: outcome.clone(),
+            };
+            jobs::jobs(filter, *limit).await?;
+        }
+        Commands::Healthcheck { url, ready } => {
+            healthcheck::healthcheck(url.clone(), *ready).await?;
+        }
         Commands::Whois { domain, json } => {
             if !json {
                 println!("Querying Whois for domain: {}", domain);
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use clap::Subcommand;
+use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Row, Table};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    models::notification::{Notification, NotificationFilter, Severity},
+    modules::channels::Channels,
+    state::{AppState, AppStateInner},
+    util, Error,
+};
+
+#[derive(Subcommand)]
+pub enum NotifyCommands {
+    /// Show which channels notifications are routed to by the `[[routes]]` rules
+    Route {
+        /// Only show the routes without sending anything (the default)
+        #[arg(long, conflicts_with = "send")]
+        dry_run: bool,
+        /// Send the notifications given by `--id` to their channels again
+        #[arg(long, requires = "ids", conflicts_with = "event")]
+        send: bool,
+        /// Notification to route, can be repeated (default: the most recent notifications)
+        #[arg(long = "id")]
+        ids: Vec<i64>,
+        /// Number of recent notifications to route
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+        /// Route a made up notification for this event instead (add, delete, change, expiry, expiry_urgent)
+        #[arg(long, requires = "domain")]
+        event: Option<String>,
+        /// Domain of the made up notification
+        #[arg(long, requires = "event")]
+        domain: Option<String>,
+        /// Provider of the made up notification
+        #[arg(long, requires = "event")]
+        provider: Option<String>,
+        /// Field changed by the made up notification ("ext_auto_renew", "metadata.status"), can be repeated
+        #[arg(long = "field", requires = "event")]
+        fields: Vec<String>,
+    },
+}
+
+impl NotifyCommands {
+    pub async fn handle(&self) -> Result<(), Error> {
+        let state: AppState = Arc::new(AppStateInner::init(false).await);
+
+        match self {
+            NotifyCommands::Route {
+                dry_run: _,
+                send,
+                ids,
+                limit,
+                event,
+                domain,
+                provider,
+                fields,
+            } => {
+                let notifications =
+                    match (event, domain) {
+                        (Some(event), Some(domain)) => {
+                            vec![made_up(event, domain, provider.clone(), fields)]
+                        }
+                        _ if !ids.is_empty() => {
+                            let mut notifications = Vec::new();
+                            for id in ids {
+                                notifications.push(
+                                    Notification::find_by_id(&state, *id).await?.ok_or_else(
+                                        || Error::msg(format!("Notification {} not found", id)),
+                                    )?,
+                                );
+                            }
+                            notifications
+                        }
+                        _ => {
+                            Notification::find_filtered(
+                                &state,
+                                &NotificationFilter::default(),
+                                None,
+                                *limit,
+                            )
+                            .await?
+                            .0
+                        }
+                    };
+
+                print_routes(&state.channels, &notifications);
+
+                if *send {
+                    state.channels.send(&state, &notifications).await;
+                    println!("Sent {} notifications", notifications.len());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn print_routes(channels: &Channels, notifications: &[Notification]) {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "Id", "Event", "Domain", "Provider", "Severity", "Route", "Channels",
+    ]);
+
+    for notification in notifications {
+        let severity = notification.severity();
+        let route = channels.router().route(notification);
+        let targets = channels
+            .names()
+            .filter(|name| channels.router().allows(notification, name))
+            .collect::<Vec<_>>();
+
+        table.add_row(Row::from(vec![
+            Cell::new(notification.id),
+            Cell::new(&notification.event),
+            Cell::new(&notification.domain),
+            Cell::new(match &notification.provider {
+                Some(provider) => util::color::colorize_provider(provider),
+                None => "-".to_string(),
+            }),
+            match severity {
+                Severity::Critical => Cell::new(severity.as_str()).fg(Color::Red),
+                Severity::High => Cell::new(severity.as_str()).fg(Color::Yellow),
+                _ => Cell::new(severity.as_str()),
+            },
+            match route {
+                Some((index, route)) => Cell::new(route.label(index)),
+                None => Cell::new("default").fg(Color::DarkGrey),
+            },
+            if targets.is_empty() {
+                Cell::new("dropped").fg(Color::DarkGrey)
+            } else {
+                Cell::new(targets.join(", "))
+            },
+        ]));
+    }
+
+    println!("{}", table);
+}
+
+/// A notification that was never stored, to try out the routes.
+/// Changed fields are switched off, like a registrar lock being removed.
+fn made_up(event: &str, domain: &str, provider: Option<String>, fields: &[String]) -> Notification {
+    let mut diff = Map::new();
+    for field in fields {
+        match field.strip_prefix("metadata.") {
+            Some(key) => {
+                let metadata = diff
+                    .entry("metadata")
+                    .or_insert_with(|| json!({ "old": {}, "new": {} }));
+                metadata["old"][key] = Value::Bool(true);
+                metadata["new"][key] = Value::Bool(false);
+            }
+            None => {
+                diff.insert(field.clone(), json!({ "old": true, "new": false }));
+            }
+        }
+    }
+
+    Notification {
+        id: 0,
+        domain: domain.to_string(),
+        event: event.to_string(),
+        message: String::new(),
+        created_at: Utc::now().naive_utc(),
+        acknowledged_at: None,
+        provider,
+        diff: (!diff.is_empty()).then_some(Value::Object(diff)),
+    }
+}
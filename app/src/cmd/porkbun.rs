@@ -2,9 +2,8 @@ use std::sync::Arc;
 
 use clap::{arg, Subcommand};
 use figment::Figment;
-use tracing::{info, warn};
 
-use crate::{modules::{domains::diff_provider, porkbun::PorkbunService, DomainService}, state::{AppState, AppStateInner}, Error};
+use crate::{models::job_run::TRIGGER_CLI, modules::{porkbun::PorkbunService, DomainService}, server::{schedule::{record, JobKind}, sync::sync_domains}, state::{AppState, AppStateInner}, Error};
 
 #[derive(Subcommand)]
 pub enum PorkbunCommands {
@@ -45,18 +44,7 @@ impl PorkbunCommands {
                 // TODO: Implement actual indexing logic
 
                 if !no_domains {
-                    let notifications = diff_provider(&state, "porkbun", &porkbun).await?;
-
-                    if let Some(ntfy) = &state.ntfy {
-                        if !notifications.is_empty() {
-                            info!("Sending notifications to Ntfy");
-                            ntfy.send_notifications(notifications).await?;
-                        } else {
-                            info!("No notifications to send");
-                        }
-                    } else {
-                        warn!("Ntfy service not initialized");
-                    }
+                    sync_domains(&state, "porkbun", &porkbun, TRIGGER_CLI).await?;
                 }
 
                 if !no_dns {
@@ -64,7 +52,14 @@ impl PorkbunCommands {
                 }
 
                 if !no_pricing {
-                    porkbun.ingest_domain_tld_prices_if_enabled(&state).await?;
+                    record(
+                        &state,
+                        JobKind::Pricing,
+                        "porkbun",
+                        TRIGGER_CLI,
+                        porkbun.ingest_domain_tld_prices_if_enabled(&state),
+                    )
+                    .await?;
                 }
 
                 Ok(())
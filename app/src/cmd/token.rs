@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use clap::Subcommand;
+use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Row, Table};
+
+use crate::{
+    models::api_token::{ApiToken, Scope},
+    state::{AppState, AppStateInner},
+    Error,
+};
+
+#[derive(Subcommand)]
+pub enum TokenCommands {
+    /// Create a new api token
+    Create {
+        /// Name used to identify the token
+        name: String,
+        /// Scope to grant (read:domains, write:domains, read:dns, write:dns, admin), can be repeated
+        #[arg(long = "scope", required = true)]
+        scopes: Vec<Scope>,
+        /// Expire the token after this duration ("30days", "1y", ...)
+        #[arg(long)]
+        expires_in: Option<humantime::Duration>,
+    },
+    /// List all api tokens
+    List,
+    /// Revoke an api token
+    Revoke {
+        /// Name of the token to revoke
+        name: String,
+    },
+}
+
+impl TokenCommands {
+    pub async fn handle(&self) -> Result<(), Error> {
+        let state: AppState = Arc::new(AppStateInner::init(false).await);
+
+        match self {
+            TokenCommands::Create {
+                name,
+                scopes,
+                expires_in,
+            } => {
+                let expires_at = match expires_in {
+                    Some(duration) => Some(Utc::now() + chrono::Duration::from_std(**duration)?),
+                    None => None,
+                };
+
+                let (token, secret) =
+                    ApiToken::new(&state, name.clone(), scopes, expires_at).await?;
+
+                println!("Created token {} ({})", token.name, token.scopes);
+                println!("This token will only be shown once:");
+                println!("{}", secret);
+            }
+            TokenCommands::List => {
+                let tokens = ApiToken::find_all(&state).await?;
+
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL);
+                table.set_content_arrangement(ContentArrangement::Dynamic);
+                table.set_header(vec!["Name", "Scopes", "Expires", "Last Used", "Created"]);
+
+                for token in tokens {
+                    let expires_cell = match token.expires_at {
+                        Some(dt) if token.is_expired() => {
+                            Cell::new(dt.format("%Y-%m-%d %H:%M:%S")).fg(Color::Red)
+                        }
+                        Some(dt) => Cell::new(dt.format("%Y-%m-%d %H:%M:%S")),
+                        None => Cell::new("never").fg(Color::DarkGrey),
+                    };
+
+                    table.add_row(Row::from(vec![
+                        Cell::new(&token.name),
+                        Cell::new(&token.scopes),
+                        expires_cell,
+                        Cell::new(match token.last_used_at {
+                            Some(dt) => chrono_humanize::HumanTime::from(dt - Utc::now()).to_string(),
+                            None => "-".to_string(),
+                        }),
+                        Cell::new(match token.created_at {
+                            Some(dt) => dt.format("%Y-%m-%d %H:%M:%S").to_string(),
+                            None => "-".to_string(),
+                        }),
+                    ]));
+                }
+
+                println!("{}", table);
+            }
+            TokenCommands::Revoke { name } => {
+                if ApiToken::delete_by_name(&state, name).await? {
+                    println!("Revoked token {}", name);
+                } else {
+                    return Err(Error::msg(format!("No token named {}", name)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::prelude::FromRow;
+
+use crate::state::AppState;
+
+const TOKEN_PREFIX: &str = "dmn_";
+
+/// Permissions that can be granted to an api token.
+/// `admin` implies every other scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "read:domains")]
+    ReadDomains,
+    #[serde(rename = "write:domains")]
+    WriteDomains,
+    #[serde(rename = "read:dns")]
+    ReadDns,
+    #[serde(rename = "write:dns")]
+    WriteDns,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadDomains => "read:domains",
+            Scope::WriteDomains => "write:domains",
+            Scope::ReadDns => "read:dns",
+            Scope::WriteDns => "write:dns",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read:domains" => Ok(Scope::ReadDomains),
+            "write:domains" => Ok(Scope::WriteDomains),
+            "read:dns" => Ok(Scope::ReadDns),
+            "write:dns" => Ok(Scope::WriteDns),
+            "admin" => Ok(Scope::Admin),
+            _ => Err(format!(
+                "Unknown scope `{}` (expected read:domains, write:domains, read:dns, write:dns or admin)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    /// Space separated list of scopes
+    pub scopes: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl ApiToken {
+    /// Create a new token, returns the stored token together with the plaintext secret.
+    /// The plaintext is never stored and can only be shown once.
+    pub async fn new(
+        state: &AppState,
+        name: String,
+        scopes: &[Scope],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(Self, String), sqlx::Error> {
+        let secret = format!("{}{}", TOKEN_PREFIX, hex::encode(rand::random::<[u8; 32]>()));
+        let scopes = scopes
+            .iter()
+            .map(|scope| scope.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let token = sqlx::query_as::<_, ApiToken>(
+            "INSERT INTO api_tokens (name, token_hash, scopes, expires_at) VALUES (?, ?, ?, ?) RETURNING *"
+        )
+        .bind(&name)
+        .bind(hash_token(&secret))
+        .bind(&scopes)
+        .bind(expires_at)
+        .fetch_one(&state.database.pool)
+        .await?;
+
+        Ok((token, secret))
+    }
+
+    pub async fn find_all(state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        let tokens = sqlx::query_as::<_, ApiToken>("SELECT * FROM api_tokens ORDER BY created_at ASC")
+            .fetch_all(&state.database.pool)
+            .await?;
+
+        Ok(tokens)
+    }
+
+    pub async fn find_by_secret(state: &AppState, secret: &str) -> Result<Option<Self>, sqlx::Error> {
+        let token = sqlx::query_as::<_, ApiToken>("SELECT * FROM api_tokens WHERE token_hash = ?")
+            .bind(hash_token(secret))
+            .fetch_optional(&state.database.pool)
+            .await?;
+
+        Ok(token)
+    }
+
+    /// Record that the token was just used
+    pub async fn touch(&self, state: &AppState) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE api_tokens SET last_used_at = ? WHERE id = ?")
+            .bind(Utc::now())
+            .bind(self.id)
+            .execute(&state.database.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Revoke a token by name, returns false if no such token exists
+    pub async fn delete_by_name(state: &AppState, name: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE name = ?")
+            .bind(name)
+            .execute(&state.database.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub fn scopes(&self) -> Vec<Scope> {
+        self.scopes
+            .split_whitespace()
+            .filter_map(|scope| scope.parse().ok())
+            .collect()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at < Utc::now())
+    }
+}
+
+pub fn hash_token(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
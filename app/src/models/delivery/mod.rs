@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, QueryBuilder, Sqlite};
+
+use crate::state::AppState;
+
+/// A single attempt at delivering a notification to a channel
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, Object)]
+pub struct Delivery {
+    pub id: i64,
+    /// Name of the channel
+    pub channel: String,
+    pub notification_id: i64,
+    /// 1 for the first attempt, counting up with every retry
+    pub attempt: i64,
+    pub success: bool,
+    /// Http status of the response, absent when no response was received
+    pub status: Option<i64>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Filters for listing deliveries, unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct DeliveryFilter {
+    pub channel: Option<String>,
+    pub notification_id: Option<i64>,
+    pub success: Option<bool>,
+}
+
+impl Delivery {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record(
+        state: &AppState,
+        channel: &str,
+        notification_id: i64,
+        attempt: i64,
+        success: bool,
+        status: Option<i64>,
+        error: Option<&str>,
+        duration_ms: i64,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Delivery>(
+            "INSERT INTO deliveries (channel, notification_id, attempt, success, status, error, duration_ms, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(channel)
+        .bind(notification_id)
+        .bind(attempt)
+        .bind(success)
+        .bind(status)
+        .bind(error)
+        .bind(duration_ms)
+        .bind(Utc::now())
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    /// The most recent attempts first
+    pub async fn find_recent(
+        state: &AppState,
+        filter: &DeliveryFilter,
+        limit: u32,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM deliveries WHERE 1 = 1");
+
+        if let Some(channel) = &filter.channel {
+            query.push(" AND channel = ").push_bind(channel.clone());
+        }
+        if let Some(notification_id) = filter.notification_id {
+            query.push(" AND notification_id = ").push_bind(notification_id);
+        }
+        if let Some(success) = filter.success {
+            query.push(" AND success = ").push_bind(success);
+        }
+
+        query
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(limit as i64);
+
+        query
+            .build_query_as::<Delivery>()
+            .fetch_all(&state.database.pool)
+            .await
+    }
+}
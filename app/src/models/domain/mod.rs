@@ -1,8 +1,8 @@
 use chrono::{DateTime, Utc};
-use poem_openapi::Object;
+use poem_openapi::{Enum, Object};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use sqlx::prelude::FromRow;
+use sqlx::{prelude::FromRow, QueryBuilder, Sqlite, SqliteConnection};
 
 use crate::state::AppState;
 
@@ -20,8 +20,88 @@ pub struct Domain {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+/// Filters for [`Domain::find_filtered`], unset fields don't filter
+#[derive(Debug, Clone, Default)]
+pub struct DomainFilter {
+    pub provider: Option<String>,
+    /// Matches the domain suffix, "dev" matches both "example.dev" and "example.pages.dev"
+    pub tld: Option<String>,
+    pub expiring_before: Option<DateTime<Utc>>,
+    pub auto_renew: Option<bool>,
+    pub whois_privacy: Option<bool>,
+    /// Matches the provider status (case insensitive)
+    pub status: Option<String>,
+    /// Substring of the domain name (case insensitive)
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Enum, Serialize, Deserialize)]
+#[oai(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DomainSort {
+    Name,
+    Provider,
+    #[default]
+    Expiry,
+    Registered,
+    Created,
+}
+
+impl DomainSort {
+    /// SQL expression used to sort (and paginate) on, NULLs sort first
+    fn column(&self) -> &'static str {
+        match self {
+            DomainSort::Name => "name",
+            DomainSort::Provider => "provider",
+            DomainSort::Expiry => "COALESCE(ext_expiry_at, '')",
+            DomainSort::Registered => "COALESCE(ext_registered_at, '')",
+            DomainSort::Created => "COALESCE(created_at, '')",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Enum, Serialize, Deserialize)]
+#[oai(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Position in a sorted listing, the sort key plus the primary key as a tiebreaker.
+/// Passed to clients as an opaque hex string.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainCursor {
+    pub sort: DomainSort,
+    pub direction: SortDirection,
+    pub key: String,
+    pub provider: String,
+    pub name: String,
+}
+
+impl DomainCursor {
+    pub fn encode(&self) -> String {
+        hex::encode(serde_json::to_vec(self).unwrap())
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = hex::decode(cursor).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+#[derive(FromRow)]
+struct SortedDomain {
+    #[sqlx(flatten)]
+    domain: Domain,
+    sort_key: String,
+}
+
 impl Domain {
-    pub async fn new(
+    /// A domain as reported by a provider, stored with [`Self::upsert`]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
         name: String,
         provider: String,
         external_id: String,
@@ -30,20 +110,35 @@ impl Domain {
         ext_auto_renew: Option<bool>,
         ext_whois_privacy: Option<bool>,
         metadata: Option<Value>,
-        state: &AppState,
-    ) -> Result<Self, sqlx::Error> {
+    ) -> Self {
+        Self {
+            name,
+            provider,
+            external_id: Some(external_id),
+            ext_expiry_at,
+            ext_registered_at,
+            ext_auto_renew,
+            ext_whois_privacy,
+            metadata,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    /// Insert or replace the domain, in the transaction of the sync that ingested it
+    pub async fn upsert(&self, conn: &mut SqliteConnection) -> Result<Self, sqlx::Error> {
         let domain = sqlx::query_as::<_, Domain>(
             "INSERT OR REPLACE INTO domains (name, provider, external_id, ext_expiry_at, ext_registered_at, ext_auto_renew, ext_whois_privacy, metadata) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING *"
         )
-        .bind(&name)
-        .bind(&provider)
-        .bind(&external_id)
-        .bind(&ext_expiry_at)
-        .bind(&ext_registered_at)
-        .bind(&ext_auto_renew)
-        .bind(&ext_whois_privacy)
-        .bind(&metadata)
-        .fetch_one(&state.database.pool)
+        .bind(&self.name)
+        .bind(&self.provider)
+        .bind(&self.external_id)
+        .bind(self.ext_expiry_at)
+        .bind(self.ext_registered_at)
+        .bind(self.ext_auto_renew)
+        .bind(self.ext_whois_privacy)
+        .bind(&self.metadata)
+        .fetch_one(conn)
         .await?;
 
         Ok(domain)
@@ -59,6 +154,102 @@ impl Domain {
         Ok(domains)
     }
 
+    /// List domains matching `filter`, sorted and paginated.
+    /// Returns the page and the cursor for the next page (if there is one).
+    pub async fn find_filtered(
+        state: &AppState,
+        filter: &DomainFilter,
+        sort: DomainSort,
+        direction: SortDirection,
+        after: Option<&DomainCursor>,
+        limit: Option<u32>,
+    ) -> Result<(Vec<Self>, Option<DomainCursor>), sqlx::Error> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT *, ");
+        query.push(sort.column()).push(" AS sort_key FROM domains WHERE 1 = 1");
+
+        if let Some(provider) = &filter.provider {
+            query.push(" AND provider = ").push_bind(provider.clone());
+        }
+        if let Some(tld) = &filter.tld {
+            let suffix = format!(".{}", tld.trim_start_matches('.').to_lowercase());
+            query
+                .push(" AND substr(lower(name), -length(")
+                .push_bind(suffix.clone())
+                .push(")) = ")
+                .push_bind(suffix);
+        }
+        if let Some(expiring_before) = filter.expiring_before {
+            query
+                .push(" AND ext_expiry_at IS NOT NULL AND ext_expiry_at <= ")
+                .push_bind(expiring_before);
+        }
+        if let Some(auto_renew) = filter.auto_renew {
+            query.push(" AND ext_auto_renew = ").push_bind(auto_renew);
+        }
+        if let Some(whois_privacy) = filter.whois_privacy {
+            query.push(" AND ext_whois_privacy = ").push_bind(whois_privacy);
+        }
+        if let Some(status) = &filter.status {
+            query
+                .push(" AND lower(COALESCE(json_extract(metadata, '$.status'), json_extract(metadata, '$.last_known_status'))) = lower(")
+                .push_bind(status.clone())
+                .push(")");
+        }
+        if let Some(name) = &filter.name {
+            query
+                .push(" AND instr(lower(name), lower(")
+                .push_bind(name.clone())
+                .push(")) > 0");
+        }
+
+        let (comparison, order) = match direction {
+            SortDirection::Asc => (">", "ASC"),
+            SortDirection::Desc => ("<", "DESC"),
+        };
+
+        if let Some(after) = after {
+            query
+                .push(format!(" AND ({}, provider, name) {} (", sort.column(), comparison))
+                .push_bind(after.key.clone())
+                .push(", ")
+                .push_bind(after.provider.clone())
+                .push(", ")
+                .push_bind(after.name.clone())
+                .push(")");
+        }
+
+        query.push(format!(
+            " ORDER BY {} {order}, provider {order}, name {order}",
+            sort.column()
+        ));
+
+        if let Some(limit) = limit {
+            // fetch one extra row to know if there is a next page
+            query.push(" LIMIT ").push_bind(limit as i64 + 1);
+        }
+
+        let mut rows = query
+            .build_query_as::<SortedDomain>()
+            .fetch_all(&state.database.pool)
+            .await?;
+
+        let next = match limit {
+            Some(limit) if rows.len() > limit as usize => {
+                rows.truncate(limit as usize);
+                rows.last().map(|row| DomainCursor {
+                    sort,
+                    direction,
+                    key: row.sort_key.clone(),
+                    provider: row.domain.provider.clone(),
+                    name: row.domain.name.clone(),
+                })
+            }
+            _ => None,
+        };
+
+        Ok((rows.into_iter().map(|row| row.domain).collect(), next))
+    }
+
     pub async fn find_by_provider(state: &AppState, provider: &str) -> Result<Vec<Self>, sqlx::Error> {
         let domains = sqlx::query_as::<_, Domain>(
             "SELECT * FROM domains WHERE provider = ?"
@@ -70,11 +261,39 @@ impl Domain {
         Ok(domains)
     }
 
-    pub async fn delete_by_name(state: &AppState, provider: &str, name: &str) -> Result<(), sqlx::Error> {
+    pub async fn find_by_provider_and_name(
+        state: &AppState,
+        provider: &str,
+        name: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let domain = sqlx::query_as::<_, Domain>(
+            "SELECT * FROM domains WHERE provider = ? AND name = ?"
+        )
+        .bind(provider)
+        .bind(name)
+        .fetch_optional(&state.database.pool)
+        .await?;
+
+        Ok(domain)
+    }
+
+    pub async fn find_by_name(state: &AppState, name: &str) -> Result<Vec<Self>, sqlx::Error> {
+        let domains = sqlx::query_as::<_, Domain>(
+            "SELECT * FROM domains WHERE name = ? ORDER BY provider ASC"
+        )
+        .bind(name)
+        .fetch_all(&state.database.pool)
+        .await?;
+
+        Ok(domains)
+    }
+
+    /// Runs on `conn` so deletions can be part of a sync transaction
+    pub async fn delete_by_name(conn: &mut SqliteConnection, provider: &str, name: &str) -> Result<(), sqlx::Error> {
         sqlx::query("DELETE FROM domains WHERE provider = ? AND name = ?")
             .bind(provider)
             .bind(name)
-            .execute(&state.database.pool)
+            .execute(conn)
             .await?;
 
         Ok(())
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use sqlx::{prelude::FromRow, SqliteConnection};
+
+use crate::{models::domain::Domain, state::AppState};
+
+/// An expiry reminder that was sent for a domain.
+/// Keyed on the expiry date, so a renewed domain gets its reminders again.
+#[derive(Debug, Clone, FromRow)]
+pub struct DomainReminder {
+    pub domain: String,
+    pub provider: String,
+    /// Days before expiry
+    pub threshold: i64,
+    pub expiry_at: DateTime<Utc>,
+    pub sent_at: DateTime<Utc>,
+}
+
+impl DomainReminder {
+    /// Thresholds that already fired for `domain` expiring at `expiry_at`
+    pub async fn find_fired(
+        state: &AppState,
+        domain: &Domain,
+        expiry_at: DateTime<Utc>,
+    ) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT threshold FROM domain_reminders WHERE domain = ? AND provider = ? AND expiry_at = ?",
+        )
+        .bind(&domain.name)
+        .bind(&domain.provider)
+        .bind(expiry_at)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    /// Mark `thresholds` as fired for `domain` expiring at `expiry_at`, in the transaction that
+    /// inserts the reminder itself
+    pub async fn record(
+        conn: &mut SqliteConnection,
+        domain: &Domain,
+        expiry_at: DateTime<Utc>,
+        thresholds: &[i64],
+    ) -> Result<(), sqlx::Error> {
+        for threshold in thresholds {
+            sqlx::query(
+                "INSERT OR IGNORE INTO domain_reminders (domain, provider, threshold, expiry_at, sent_at) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(&domain.name)
+            .bind(&domain.provider)
+            .bind(threshold)
+            .bind(expiry_at)
+            .bind(Utc::now())
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,13 +1,15 @@
 use chrono::{DateTime, Utc};
+use poem_openapi::Object;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
 use crate::state::AppState;
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Serialize, Deserialize, FromRow, Object)]
 pub struct DomainTLDPrice {
     pub provider: String,
     pub tld: String,
+    /// Registration price in cents
     pub price: i64,
     pub updated_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
@@ -31,4 +33,26 @@ impl DomainTLDPrice {
 
         Ok(domain_tld_price)
     }
+
+    /// Find the prices for the tld of a domain, matching the longest known suffix
+    /// (`example.co.uk` matches `co.uk` before `uk`)
+    pub async fn find_for_domain(state: &AppState, name: &str) -> Result<Vec<Self>, sqlx::Error> {
+        let labels = name.split('.').collect::<Vec<_>>();
+
+        for i in 1..labels.len() {
+            let tld = labels[i..].join(".");
+            let prices = sqlx::query_as::<_, DomainTLDPrice>(
+                "SELECT * FROM domain_tld_prices WHERE tld = ? ORDER BY provider ASC"
+            )
+            .bind(&tld)
+            .fetch_all(&state.database.pool)
+            .await?;
+
+            if !prices.is_empty() {
+                return Ok(prices);
+            }
+        }
+
+        Ok(vec![])
+    }
 }
@@ -0,0 +1,191 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, QueryBuilder, Sqlite};
+
+use crate::{models::notification::Notification, state::AppState};
+
+pub const OUTCOME_RUNNING: &str = "running";
+pub const OUTCOME_SUCCESS: &str = "success";
+pub const OUTCOME_FAILURE: &str = "failure";
+/// Another sync for the same provider was already running
+pub const OUTCOME_SKIPPED: &str = "skipped";
+
+pub const TRIGGER_SCHEDULE: &str = "schedule";
+pub const TRIGGER_API: &str = "api";
+pub const TRIGGER_CLI: &str = "cli";
+
+/// A single run of a background job (domain sync, dns sync, pricing, ...)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, Object)]
+pub struct JobRun {
+    pub id: i64,
+    /// "domains", "dns", "pricing"
+    pub kind: String,
+    pub provider: Option<String>,
+    /// What started the run: "schedule", "api" or "cli"
+    pub trigger: String,
+    /// "running", "success", "failure" or "skipped"
+    pub outcome: String,
+    pub error: Option<String>,
+    pub added: i64,
+    pub deleted: i64,
+    pub changed: i64,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Filters for listing job runs, unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct JobRunFilter {
+    pub kind: Option<String>,
+    pub provider: Option<String>,
+    pub outcome: Option<String>,
+}
+
+impl JobRun {
+    pub async fn start(
+        state: &AppState,
+        kind: &str,
+        provider: Option<&str>,
+        trigger: &str,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, JobRun>(
+            "INSERT INTO job_runs (kind, provider, trigger, started_at) VALUES (?, ?, ?, ?) RETURNING *",
+        )
+        .bind(kind)
+        .bind(provider)
+        .bind(trigger)
+        .bind(Utc::now())
+        .fetch_one(&state.database.pool)
+        .await
+    }
+
+    /// Mark the run as successful, counting the notifications it produced
+    pub async fn succeed(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), sqlx::Error> {
+        let count = |event: &str| {
+            notifications
+                .iter()
+                .filter(|notification| notification.event == event)
+                .count() as i64
+        };
+
+        sqlx::query(
+            "UPDATE job_runs SET outcome = ?, added = ?, deleted = ?, changed = ?, finished_at = ? WHERE id = ?",
+        )
+        .bind(OUTCOME_SUCCESS)
+        .bind(count("add"))
+        .bind(count("delete"))
+        .bind(count("change"))
+        .bind(Utc::now())
+        .bind(self.id)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fail(&self, state: &AppState, error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE job_runs SET outcome = ?, error = ?, finished_at = ? WHERE id = ?")
+            .bind(OUTCOME_FAILURE)
+            .bind(error)
+            .bind(Utc::now())
+            .bind(self.id)
+            .execute(&state.database.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn skip(&self, state: &AppState, reason: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE job_runs SET outcome = ?, error = ?, finished_at = ? WHERE id = ?")
+            .bind(OUTCOME_SKIPPED)
+            .bind(reason)
+            .bind(Utc::now())
+            .bind(self.id)
+            .execute(&state.database.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Runs still marked as running were interrupted by a restart, mark them as failed.
+    /// Runs holding a sync lock belong to another process (e.g. the cli) and are left alone.
+    pub async fn fail_interrupted(state: &AppState) -> Result<u64, sqlx::Error> {
+        let now = Utc::now();
+        let result = sqlx::query(
+            "UPDATE job_runs SET outcome = ?, error = 'Interrupted', finished_at = ?
+            WHERE outcome = ? AND id NOT IN (SELECT job_run_id FROM sync_locks WHERE expires_at >= ?)",
+        )
+        .bind(OUTCOME_FAILURE)
+        .bind(now)
+        .bind(OUTCOME_RUNNING)
+        .bind(now)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// The most recent runs first
+    pub async fn find_recent(
+        state: &AppState,
+        filter: &JobRunFilter,
+        limit: u32,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM job_runs WHERE 1 = 1");
+
+        if let Some(kind) = &filter.kind {
+            query.push(" AND kind = ").push_bind(kind.clone());
+        }
+        if let Some(provider) = &filter.provider {
+            query.push(" AND provider = ").push_bind(provider.clone());
+        }
+        if let Some(outcome) = &filter.outcome {
+            query.push(" AND outcome = ").push_bind(outcome.clone());
+        }
+
+        query
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(limit as i64);
+
+        query
+            .build_query_as::<JobRun>()
+            .fetch_all(&state.database.pool)
+            .await
+    }
+
+    /// The latest run of a job for any provider or trigger, skipped runs didn't do anything
+    pub async fn find_last_started(
+        state: &AppState,
+        kind: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, JobRun>(
+            "SELECT * FROM job_runs WHERE kind = ? AND outcome != ? ORDER BY started_at DESC LIMIT 1",
+        )
+        .bind(kind)
+        .bind(OUTCOME_SKIPPED)
+        .fetch_optional(&state.database.pool)
+        .await
+    }
+
+    /// The latest successful or failed run of a job for a provider
+    pub async fn find_last_finished(
+        state: &AppState,
+        kind: &str,
+        provider: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, JobRun>(
+            "SELECT * FROM job_runs WHERE kind = ? AND provider = ? AND outcome IN (?, ?) ORDER BY id DESC LIMIT 1",
+        )
+        .bind(kind)
+        .bind(provider)
+        .bind(OUTCOME_SUCCESS)
+        .bind(OUTCOME_FAILURE)
+        .fetch_optional(&state.database.pool)
+        .await
+    }
+}
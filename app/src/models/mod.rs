@@ -1,3 +1,9 @@
+pub mod api_token;
 pub mod domain;
 pub mod notification;
 pub mod domain_tld_price;
+pub mod job_run;
+pub mod domain_reminder;
+pub mod sync_lock;
+pub mod delivery;
+pub mod notification_queue;
@@ -1,6 +1,18 @@
+use chrono::NaiveDateTime;
 use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
 
-use crate::{state::AppState, Error};
+use crate::{state::AppState, util::value::bool_from_value, Error};
+
+/// A domain is about to expire
+pub const EVENT_EXPIRY: &str = "expiry";
+/// A domain is about to expire and won't be renewed automatically
+pub const EVENT_EXPIRY_URGENT: &str = "expiry_urgent";
+
+/// Metadata keys of the registrar lock, porkbun's `security_lock` & cloudflare's `locked`
+const LOCK_KEYS: [&str; 2] = ["security_lock", "locked"];
 
 #[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow, Object)]
 pub struct Notification {
@@ -8,19 +20,133 @@ pub struct Notification {
     pub domain: String,
     pub event: String,
     pub message: String,
-    pub created_at: chrono::NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub acknowledged_at: Option<NaiveDateTime>,
+    pub provider: Option<String>,
+    /// Changed fields as `{"field": {"old": .., "new": ..}}`, only for "change" notifications
+    pub diff: Option<Value>,
+}
+
+/// How urgent a notification is, push channels map it to their own priorities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// New domains
+    Low,
+    /// Changes & expiry reminders
+    Normal,
+    /// Domains expiring without auto renew
+    High,
+    /// Deleted domains & lost registrar locks
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Low => "low",
+            Severity::Normal => "normal",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// Filters for listing notifications, unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct NotificationFilter {
+    pub domain: Option<String>,
+    pub event: Option<String>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    pub acknowledged: Option<bool>,
 }
 
 impl Notification {
-    pub async fn new(state: &AppState, domain: String, event: &str, message: String) -> Result<Self, Error> {
+    pub fn severity(&self) -> Severity {
+        match self.event.as_str() {
+            "delete" => Severity::Critical,
+            "change" if self.lost_lock() => Severity::Critical,
+            EVENT_EXPIRY_URGENT => Severity::High,
+            "add" => Severity::Low,
+            _ => Severity::Normal,
+        }
+    }
+
+    /// Whether the change removed the registrar lock, leaving the domain open to transfers
+    pub fn lost_lock(&self) -> bool {
+        let Some(metadata) = self.diff.as_ref().and_then(|diff| diff.get("metadata")) else {
+            return false;
+        };
+
+        LOCK_KEYS.iter().any(|key| {
+            match (metadata["old"].get(key), metadata["new"].get(key)) {
+                (Some(old), Some(new)) => bool_from_value(old) && !bool_from_value(new),
+                _ => false,
+            }
+        })
+    }
+
+    /// Fields touched by a change, metadata keys as "metadata.<key>"
+    pub fn changed_fields(&self) -> Vec<String> {
+        let Some(Value::Object(diff)) = &self.diff else {
+            return Vec::new();
+        };
+
+        let mut fields = Vec::new();
+        for (field, change) in diff {
+            match (&change["old"], &change["new"]) {
+                (Value::Object(old), Value::Object(new)) if field == "metadata" => {
+                    for key in old.keys().chain(new.keys().filter(|key| !old.contains_key(*key))) {
+                        if old.get(key) != new.get(key) {
+                            fields.push(format!("metadata.{}", key));
+                        }
+                    }
+                }
+                _ => fields.push(field.clone()),
+            }
+        }
+
+        fields
+    }
+
+    pub async fn new(
+        state: &AppState,
+        domain: String,
+        provider: &str,
+        event: &str,
+        message: String,
+        diff: Option<Value>,
+    ) -> Result<Self, Error> {
+        let mut conn = state.database.pool.acquire().await?;
+        let notification = Self::insert(&mut conn, domain, provider, event, message, diff).await?;
+
+        state.notification_bus.publish(&notification);
+
+        Ok(notification)
+    }
+
+    /// Insert without publishing to the live stream, for notifications written in a transaction.
+    /// Publish them once the transaction is committed.
+    pub async fn insert(
+        conn: &mut SqliteConnection,
+        domain: String,
+        provider: &str,
+        event: &str,
+        message: String,
+        diff: Option<Value>,
+    ) -> Result<Self, Error> {
         let notification = sqlx::query_as!(
             Notification,
-            "INSERT INTO notifications (domain, event, message) VALUES (?, ?, ?) RETURNING *",
+            r#"INSERT INTO notifications (domain, provider, event, message, diff) VALUES (?, ?, ?, ?, ?)
+            RETURNING id, domain, event, message, created_at, acknowledged_at, provider, diff as "diff: Value""#,
             domain,
+            provider,
             event,
-            message
+            message,
+            diff
         )
-        .fetch_one(&state.database.pool)
+        .fetch_one(conn)
         .await?;
 
         Ok(notification)
@@ -29,11 +155,145 @@ impl Notification {
     pub async fn find_all(state: &AppState) -> Result<Vec<Self>, Error> {
         let notifications = sqlx::query_as!(
             Notification,
-            "SELECT * FROM notifications"
+            r#"SELECT id, domain, event, message, created_at, acknowledged_at, provider, diff as "diff: Value" FROM notifications"#
         )
         .fetch_all(&state.database.pool)
         .await?;
 
         Ok(notifications)
     }
+
+    pub async fn find_by_domain(state: &AppState, domain: &str) -> Result<Vec<Self>, Error> {
+        let notifications = sqlx::query_as::<_, Notification>(
+            "SELECT * FROM notifications WHERE domain = ? ORDER BY created_at DESC"
+        )
+        .bind(domain)
+        .fetch_all(&state.database.pool)
+        .await?;
+
+        Ok(notifications)
+    }
+
+    pub async fn find_by_id(state: &AppState, id: i64) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Notification>("SELECT * FROM notifications WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&state.database.pool)
+            .await
+    }
+
+    /// List notifications newest first.
+    /// Returns a page of at most `limit` notifications and the id to pass as `before`
+    /// to fetch the next page.
+    pub async fn find_filtered(
+        state: &AppState,
+        filter: &NotificationFilter,
+        before: Option<i64>,
+        limit: u32,
+    ) -> Result<(Vec<Self>, Option<i64>), sqlx::Error> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM notifications WHERE 1 = 1");
+
+        if let Some(domain) = &filter.domain {
+            query.push(" AND domain = ").push_bind(domain.clone());
+        }
+        if let Some(event) = &filter.event {
+            query.push(" AND event = ").push_bind(event.clone());
+        }
+        if let Some(since) = filter.since {
+            query.push(" AND created_at >= ").push_bind(since);
+        }
+        if let Some(until) = filter.until {
+            query.push(" AND created_at < ").push_bind(until);
+        }
+        match filter.acknowledged {
+            Some(true) => {
+                query.push(" AND acknowledged_at IS NOT NULL");
+            }
+            Some(false) => {
+                query.push(" AND acknowledged_at IS NULL");
+            }
+            None => {}
+        }
+        if let Some(before) = before {
+            query.push(" AND id < ").push_bind(before);
+        }
+
+        // fetch one extra row to know if there is a next page
+        query
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(limit as i64 + 1);
+
+        let mut notifications = query
+            .build_query_as::<Notification>()
+            .fetch_all(&state.database.pool)
+            .await?;
+
+        let next = if notifications.len() > limit as usize {
+            notifications.truncate(limit as usize);
+            notifications.last().map(|notification| notification.id)
+        } else {
+            None
+        };
+
+        Ok((notifications, next))
+    }
+
+    /// Mark the notification as acknowledged, acknowledging twice keeps the first timestamp
+    pub async fn acknowledge(state: &AppState, id: i64) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Notification>(
+            "UPDATE notifications SET acknowledged_at = COALESCE(acknowledged_at, CURRENT_TIMESTAMP) WHERE id = ? RETURNING *",
+        )
+        .bind(id)
+        .fetch_optional(&state.database.pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+impl Notification {
+    /// A notification that isn't stored, tests set the fields they care about on top
+    pub fn test(domain: &str, event: &str) -> Self {
+        Self {
+            id: 1,
+            domain: domain.to_string(),
+            event: event.to_string(),
+            message: String::new(),
+            created_at: NaiveDateTime::default(),
+            acknowledged_at: None,
+            provider: Some("porkbun".to_string()),
+            diff: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_severity() {
+        let mut notification = Notification {
+            diff: Some(json!({
+                "metadata": {
+                    "old": { "security_lock": 1, "status": "ACTIVE" },
+                    "new": { "security_lock": 0, "status": "ACTIVE" },
+                }
+            })),
+            ..Notification::test("example.com", "change")
+        };
+        assert_eq!(notification.severity(), Severity::Critical);
+
+        // locking a domain is routine
+        notification.diff = Some(json!({
+            "metadata": {
+                "old": { "locked": false },
+                "new": { "locked": true },
+            }
+        }));
+        assert_eq!(notification.severity(), Severity::Normal);
+
+        notification.event = "add".to_string();
+        assert_eq!(notification.severity(), Severity::Low);
+    }
 }
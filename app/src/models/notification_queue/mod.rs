@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use sqlx::prelude::FromRow;
+
+use crate::{models::notification::Notification, state::AppState};
+
+/// A notification held back for a channel until `release_at`, by a digest or quiet hours.
+/// The notification itself stays in `notifications`, the queue only keeps its release per channel
+/// as every channel has its own schedule & one notification can wait for several of them.
+#[derive(Debug, Clone, FromRow)]
+pub struct QueuedNotification {
+    pub channel: String,
+    pub notification_id: i64,
+    pub release_at: DateTime<Utc>,
+    pub queued_at: DateTime<Utc>,
+}
+
+impl QueuedNotification {
+    pub async fn enqueue(
+        state: &AppState,
+        channel: &str,
+        notification_ids: &[i64],
+        release_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        for notification_id in notification_ids {
+            sqlx::query(
+                "INSERT OR IGNORE INTO notification_queue (channel, notification_id, release_at, queued_at) VALUES (?, ?, ?, ?)",
+            )
+            .bind(channel)
+            .bind(notification_id)
+            .bind(release_at)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Notifications of `channel` due for release at `now`, oldest first
+    pub async fn find_due(
+        state: &AppState,
+        channel: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Notification>, sqlx::Error> {
+        sqlx::query_as::<_, Notification>(
+            "SELECT notifications.* FROM notification_queue JOIN notifications ON notifications.id = notification_queue.notification_id WHERE notification_queue.channel = ? AND notification_queue.release_at <= ? ORDER BY notifications.id",
+        )
+        .bind(channel)
+        .bind(now)
+        .fetch_all(&state.database.pool)
+        .await
+    }
+
+    /// Remove released notifications from the queue of `channel`
+    pub async fn release(
+        state: &AppState,
+        channel: &str,
+        notification_ids: &[i64],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        for notification_id in notification_ids {
+            sqlx::query("DELETE FROM notification_queue WHERE channel = ? AND notification_id = ?")
+                .bind(channel)
+                .bind(notification_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Retry a release that failed at `release_at`
+    pub async fn postpone(
+        state: &AppState,
+        channel: &str,
+        notification_ids: &[i64],
+        release_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        for notification_id in notification_ids {
+            sqlx::query(
+                "UPDATE notification_queue SET release_at = ? WHERE channel = ? AND notification_id = ?",
+            )
+            .bind(release_at)
+            .bind(channel)
+            .bind(notification_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+}
@@ -0,0 +1,181 @@
+use std::{fmt, time::Duration};
+
+use async_std::task;
+use chrono::{DateTime, Utc};
+use sqlx::prelude::FromRow;
+use tracing::warn;
+
+use crate::{models::job_run::JobRun, state::AppState, Error};
+
+/// How long a lease is valid without being renewed, a crashed process blocks syncs at most this long
+const LEASE_DURATION: Duration = Duration::from_secs(2 * 60);
+const RENEW_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Lease on syncing a provider, shared between every process using the database
+#[derive(Debug, Clone, FromRow)]
+pub struct SyncLock {
+    pub provider: String,
+    /// The job run holding the lease
+    pub job_run_id: i64,
+    /// Who holds the lease, e.g. "cli (pid 1234)"
+    pub holder: String,
+    pub acquired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Returned when a provider can't be synced because another sync holds the lock
+#[derive(Debug)]
+pub struct SyncAlreadyRunning(pub SyncLock);
+
+impl fmt::Display for SyncAlreadyRunning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Sync for {} already running (job {}, {}) since {}",
+            self.0.provider,
+            self.0.job_run_id,
+            self.0.holder,
+            self.0.acquired_at.format("%Y-%m-%d %H:%M:%S UTC")
+        )
+    }
+}
+
+impl std::error::Error for SyncAlreadyRunning {}
+
+impl SyncLock {
+    /// Take the lock for `provider` on behalf of `run`, `None` if another run holds it.
+    /// Expired leases are taken over.
+    pub async fn acquire(
+        state: &AppState,
+        provider: &str,
+        run: &JobRun,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let now = Utc::now();
+
+        sqlx::query_as::<_, SyncLock>(
+            "INSERT INTO sync_locks (provider, job_run_id, holder, acquired_at, expires_at) VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (provider) DO UPDATE SET
+                job_run_id = excluded.job_run_id,
+                holder = excluded.holder,
+                acquired_at = excluded.acquired_at,
+                expires_at = excluded.expires_at
+            WHERE sync_locks.expires_at < excluded.acquired_at
+            RETURNING *",
+        )
+        .bind(provider)
+        .bind(run.id)
+        .bind(format!("{} (pid {})", run.trigger, std::process::id()))
+        .bind(now)
+        .bind(now + LEASE_DURATION)
+        .fetch_optional(&state.database.pool)
+        .await
+    }
+
+    /// The current lease for `provider`, if it hasn't expired
+    pub async fn find_active(state: &AppState, provider: &str) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, SyncLock>(
+            "SELECT * FROM sync_locks WHERE provider = ? AND expires_at >= ?",
+        )
+        .bind(provider)
+        .bind(Utc::now())
+        .fetch_optional(&state.database.pool)
+        .await
+    }
+
+    /// Extend the lease, `false` once it expired or was taken over by another run
+    pub async fn renew(&self, state: &AppState) -> Result<bool, sqlx::Error> {
+        let now = Utc::now();
+
+        let result = sqlx::query(
+            "UPDATE sync_locks SET expires_at = ? WHERE provider = ? AND job_run_id = ? AND expires_at >= ?",
+        )
+        .bind(now + LEASE_DURATION)
+        .bind(&self.provider)
+        .bind(self.job_run_id)
+        .bind(now)
+        .execute(&state.database.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Renew the lease until the future is dropped, race it against the work holding the lock.
+    /// Resolves with an error once the lease is lost, so the work is aborted.
+    pub async fn keep_alive<T>(&self, state: &AppState) -> Result<T, Error> {
+        let mut expires_at = self.expires_at;
+
+        loop {
+            task::sleep(RENEW_INTERVAL).await;
+
+            match self.renew(state).await {
+                Ok(true) => expires_at = Utc::now() + LEASE_DURATION,
+                Ok(false) => {
+                    return Err(Error::msg(format!(
+                        "Lost sync lock for {}, the lease expired or was taken over",
+                        self.provider
+                    )))
+                }
+                Err(e) => {
+                    warn!("Failed to renew sync lock for {}: {}", self.provider, e);
+
+                    if Utc::now() >= expires_at {
+                        return Err(Error::msg(format!(
+                            "Lost sync lock for {}, the lease expired without being renewed",
+                            self.provider
+                        )));
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn release(&self, state: &AppState) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM sync_locks WHERE provider = ? AND job_run_id = ?")
+            .bind(&self.provider)
+            .bind(self.job_run_id)
+            .execute(&state.database.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::job_run::{JobRun, TRIGGER_CLI, TRIGGER_SCHEDULE},
+        state::AppStateInner,
+    };
+
+    use super::*;
+
+    #[async_std::test]
+    async fn test_renew() {
+        let state = AppStateInner::test().await;
+        let first = JobRun::start(&state, "domains", Some("porkbun"), TRIGGER_CLI)
+            .await
+            .unwrap();
+        let second = JobRun::start(&state, "domains", Some("porkbun"), TRIGGER_SCHEDULE)
+            .await
+            .unwrap();
+
+        let lock = SyncLock::acquire(&state, "porkbun", &first)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(lock.renew(&state).await.unwrap());
+
+        sqlx::query("UPDATE sync_locks SET expires_at = ?")
+            .bind(Utc::now() - LEASE_DURATION)
+            .execute(&state.database.pool)
+            .await
+            .unwrap();
+        assert!(!lock.renew(&state).await.unwrap());
+
+        SyncLock::acquire(&state, "porkbun", &second)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!lock.renew(&state).await.unwrap());
+    }
+}
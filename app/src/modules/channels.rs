@@ -0,0 +1,497 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use figment::{
+    providers::Serialized,
+    value::{Dict, Value},
+    Figment,
+};
+use serde_json::Value as JsonValue;
+use tracing::{error, info, warn};
+
+use crate::{
+    models::{
+        notification::{Notification, EVENT_EXPIRY, EVENT_EXPIRY_URGENT},
+        notification_queue::QueuedNotification,
+    },
+    modules::{
+        digest::{DeliveryConfig, DeliverySchedule},
+        discord::DiscordChannel,
+        gotify::GotifyChannel,
+        matrix::MatrixChannel,
+        ntfy::NtfyService,
+        pushover::PushoverChannel,
+        routing::Router,
+        slack::SlackChannel,
+        smtp::SmtpChannel,
+        webhook::WebhookChannel,
+        NotificationChannel,
+    },
+    state::AppState,
+    util::value::bool_from_value,
+    Error,
+};
+
+/// A configured notification channel
+pub struct Channel {
+    pub name: String,
+    /// The `type` of the channel ("ntfy", "webhook", ...)
+    pub kind: String,
+    pub channel: Box<dyn NotificationChannel>,
+    /// Digest & quiet hours
+    pub schedule: DeliverySchedule,
+}
+
+/// How long a failed release of queued notifications waits before trying again
+const RELEASE_RETRY: TimeDelta = TimeDelta::minutes(15);
+
+/// Every configured notification channel, the `[[routes]]` decide which notifications go where
+#[derive(Default)]
+pub struct Channels {
+    channels: Vec<Channel>,
+    router: Router,
+}
+
+impl Channels {
+    /// Load the named channels from the `[channels.<name>]` tables, each with a `type`
+    pub async fn load(config_file: &Figment) -> Self {
+        let configured = config_file
+            .clone()
+            .focus("channels")
+            .extract::<BTreeMap<String, Dict>>()
+            .unwrap_or_else(|error| {
+                warn!("Failed to load channels config: {}", error);
+                BTreeMap::new()
+            });
+
+        let mut channels = Vec::new();
+        for (name, options) in configured {
+            let kind = match options.get("type") {
+                Some(Value::String(_, kind)) => kind.clone(),
+                _ => {
+                    warn!("Channel {} has no type, skipping", name);
+                    continue;
+                }
+            };
+
+            let config = Figment::from(Serialized::defaults(options));
+            let schedule = config
+                .extract::<DeliveryConfig>()
+                .map_err(Error::from)
+                .and_then(DeliverySchedule::new);
+            match (build(&name, &kind, &config), schedule) {
+                (Ok(channel), Ok(schedule)) => {
+                    info!("Notification channel {} ({}) configured", name, kind);
+                    channels.push(Channel {
+                        name,
+                        kind,
+                        channel,
+                        schedule,
+                    });
+                }
+                (Err(e), _) | (_, Err(e)) => {
+                    warn!("Failed to configure channel {} ({}): {}", name, kind, e)
+                }
+            }
+        }
+
+        // ntfy configured the old way, through `NTFY_` variables or top level keys
+        if !channels.iter().any(|channel| channel.kind == "ntfy") {
+            if let Some(ntfy) = NtfyService::try_init(config_file).await {
+                channels.push(Channel {
+                    name: "ntfy".to_string(),
+                    kind: "ntfy".to_string(),
+                    channel: Box::new(ntfy),
+                    schedule: DeliverySchedule::default(),
+                });
+            }
+        }
+
+        let router = Router::load(config_file);
+        for route in router.routes() {
+            for name in &route.channels {
+                if !channels.iter().any(|channel| &channel.name == name) {
+                    warn!("Route sends to unknown channel {}", name);
+                }
+            }
+        }
+
+        Self { channels, router }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.channels.iter().map(|channel| channel.name.as_str())
+    }
+
+    pub fn router(&self) -> &Router {
+        &self.router
+    }
+
+    /// Send the notifications of a sync to the channels they are routed to, a failing channel
+    /// doesn't stop the others
+    pub async fn send(&self, state: &AppState, notifications: &[Notification]) {
+        if notifications.is_empty() {
+            info!("No notifications to send");
+            return;
+        }
+
+        if self.channels.is_empty() {
+            warn!("No notification channels configured");
+            return;
+        }
+
+        let now = Utc::now();
+        for channel in &self.channels {
+            let mut immediate = Vec::new();
+            let mut queued = BTreeMap::<DateTime<Utc>, Vec<i64>>::new();
+
+            let routed = notifications
+                .iter()
+                .filter(|notification| self.router.allows(notification, &channel.name));
+            for notification in routed {
+                match channel.schedule.release_at(now, notification.severity()) {
+                    Some(release_at) => queued.entry(release_at).or_default().push(notification.id),
+                    None => immediate.push(notification.clone()),
+                }
+            }
+
+            for (release_at, ids) in queued {
+                info!(
+                    "Holding back {} notifications for {} until {}",
+                    ids.len(),
+                    channel.name,
+                    release_at
+                );
+                if let Err(e) = QueuedNotification::enqueue(state, &channel.name, &ids, release_at).await {
+                    error!("Failed to queue notifications for {}: {}", channel.name, e);
+                }
+            }
+
+            if immediate.is_empty() {
+                continue;
+            }
+
+            info!("Sending {} notifications to {}", immediate.len(), channel.name);
+            if let Err(e) = channel.channel.send_notifications(state, &immediate).await {
+                error!("Failed to send notifications to {}: {}", channel.name, e);
+            }
+        }
+    }
+
+    /// Send the queued notifications that are due as one summary per channel
+    pub async fn release(&self, state: &AppState, now: DateTime<Utc>) {
+        for channel in &self.channels {
+            let notifications = match QueuedNotification::find_due(state, &channel.name, now).await {
+                Ok(notifications) => notifications,
+                Err(e) => {
+                    error!("Failed to load queued notifications for {}: {}", channel.name, e);
+                    continue;
+                }
+            };
+
+            if notifications.is_empty() {
+                continue;
+            }
+
+            let ids = notifications.iter().map(|notification| notification.id).collect::<Vec<_>>();
+            info!("Releasing {} queued notifications to {}", ids.len(), channel.name);
+
+            // only what wasn't delivered is retried, webhooks would deliver the rest twice
+            let (delivered, failed) =
+                match channel.channel.release_notifications(state, &notifications).await {
+                    Ok(delivered) => {
+                        let failed = ids
+                            .iter()
+                            .copied()
+                            .filter(|id| !delivered.contains(id))
+                            .collect::<Vec<_>>();
+                        (delivered, failed)
+                    }
+                    Err(e) => {
+                        error!("Failed to release notifications to {}: {}", channel.name, e);
+                        (Vec::new(), ids)
+                    }
+                };
+
+            if !failed.is_empty() {
+                error!(
+                    "{} notifications not released to {}, retrying in {} minutes",
+                    failed.len(),
+                    channel.name,
+                    RELEASE_RETRY.num_minutes()
+                );
+            }
+
+            let result = async {
+                QueuedNotification::release(state, &channel.name, &delivered).await?;
+                QueuedNotification::postpone(state, &channel.name, &failed, now + RELEASE_RETRY).await
+            }
+            .await;
+
+            if let Err(e) = result {
+                error!("Failed to update the queue of {}: {}", channel.name, e);
+            }
+        }
+    }
+}
+
+/// Create a channel of type `kind`, every channel module is registered here
+fn build(name: &str, kind: &str, config: &Figment) -> Result<Box<dyn NotificationChannel>, Error> {
+    match kind {
+        "ntfy" => Ok(Box::new(NtfyService::init(config.extract()?))),
+        "webhook" => Ok(Box::new(WebhookChannel::new(name, config.extract()?)?)),
+        "smtp" => Ok(Box::new(SmtpChannel::new(config.extract()?)?)),
+        "discord" => Ok(Box::new(DiscordChannel::new(config.extract()?))),
+        "slack" => Ok(Box::new(SlackChannel::new(config.extract()?))),
+        "matrix" => Ok(Box::new(MatrixChannel::new(config.extract()?)?)),
+        "gotify" => Ok(Box::new(GotifyChannel::new(config.extract()?))),
+        "pushover" => Ok(Box::new(PushoverChannel::new(config.extract()?))),
+        _ => Err(Error::msg(format!("Unknown channel type {}", kind))),
+    }
+}
+
+/// Events in the order channels present them, most pressing first
+const EVENT_ORDER: [&str; 5] = ["delete", EVENT_EXPIRY_URGENT, EVENT_EXPIRY, "change", "add"];
+
+/// Group the notifications of a sync by event, so a channel can send one message per group
+pub fn group_by_event(notifications: &[Notification]) -> Vec<(&str, Vec<&Notification>)> {
+    let mut groups: Vec<(&str, Vec<&Notification>)> = Vec::new();
+
+    for notification in notifications {
+        match groups.iter_mut().find(|(event, _)| *event == notification.event) {
+            Some((_, group)) => group.push(notification),
+            None => groups.push((&notification.event, vec![notification])),
+        }
+    }
+
+    // unknown events go last
+    groups.sort_by_key(|(event, _)| {
+        EVENT_ORDER
+            .iter()
+            .position(|known| known == event)
+            .unwrap_or(EVENT_ORDER.len())
+    });
+
+    groups
+}
+
+/// Title for a group of `count` notifications of the same event
+pub fn event_title(event: &str, count: usize) -> String {
+    let plural = if count > 1 { "s" } else { "" };
+
+    match event {
+        "add" => format!("New Domain{}", plural),
+        "delete" => format!("Domain{} Deleted", plural),
+        "change" => format!("Domain{} Changed", plural),
+        EVENT_EXPIRY => format!("Domain{} Expiring", plural),
+        EVENT_EXPIRY_URGENT => format!("Domain{} Expiring, Renew Manually", plural),
+        _ => "Unknown".to_string(),
+    }
+}
+
+/// "1 Domain Deleted, 2 New Domains"
+pub fn summarize(notifications: &[Notification]) -> String {
+    group_by_event(notifications)
+        .iter()
+        .map(|(event, group)| format!("{} {}", group.len(), event_title(event, group.len())))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Split a group of notifications by provider, keeping their order
+pub fn group_by_provider<'a>(
+    notifications: &[&'a Notification],
+) -> Vec<(Option<&'a str>, Vec<&'a Notification>)> {
+    let mut groups: Vec<(Option<&str>, Vec<&Notification>)> = Vec::new();
+
+    for notification in notifications {
+        let provider = notification.provider.as_deref();
+        match groups.iter_mut().find(|(known, _)| *known == provider) {
+            Some((_, group)) => group.push(notification),
+            None => groups.push((provider, vec![notification])),
+        }
+    }
+
+    groups
+}
+
+/// The changed fields of a "change" notification, one per line, rendered from its diff
+pub fn change_lines(notification: &Notification) -> Vec<String> {
+    let Some(JsonValue::Object(diff)) = &notification.diff else {
+        return Vec::new();
+    };
+
+    let mut lines = Vec::new();
+    for (field, change) in diff {
+        let old = change.get("old").unwrap_or(&JsonValue::Null);
+        let new = change.get("new").unwrap_or(&JsonValue::Null);
+
+        match (field.as_str(), old, new) {
+            // metadata is a single field, list what changed inside it
+            ("metadata", JsonValue::Object(old), JsonValue::Object(new)) => {
+                let keys = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+                for key in keys {
+                    let old = old.get(key).unwrap_or(&JsonValue::Null);
+                    let new = new.get(key).unwrap_or(&JsonValue::Null);
+                    if old != new {
+                        lines.push(change_line(key, old, new));
+                    }
+                }
+            }
+            (field, old, new) => lines.push(change_line(field, old, new)),
+        }
+    }
+
+    lines
+}
+
+/// "Auto Renew Changed: true => false"
+fn change_line(field: &str, old: &JsonValue, new: &JsonValue) -> String {
+    match field {
+        "ext_auto_renew" | "security_lock" => {
+            let label = if field == "ext_auto_renew" { "Auto Renew" } else { "Security Lock" };
+            format!(
+                "{} Changed: {} => {}",
+                label,
+                bool_from_value(old),
+                bool_from_value(new)
+            )
+        }
+        "status" => format!("Status Changed: {} => {}", display(old), display(new)),
+        _ => format!("{}: {} => {}", field, display(old), display(new)),
+    }
+}
+
+/// Strings without their quotes
+fn display(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Plain list of notifications for push channels, the changed fields indented below their domain
+pub fn render_list(notifications: &[&Notification]) -> String {
+    let mut lines = Vec::new();
+
+    for notification in notifications {
+        match (notification.event.as_str(), &notification.provider) {
+            ("add" | "delete" | "change", Some(provider)) => {
+                lines.push(format!("- {} ({})", notification.domain, provider))
+            }
+            _ => lines.push(format!("- {}", notification.message)),
+        }
+
+        for line in change_lines(notification) {
+            lines.push(format!("  - {}", line));
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::providers::{Format, Toml};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        state::AppStateInner,
+        util::stub::{StubResponse, StubServer},
+    };
+
+    #[test]
+    fn test_change_lines() {
+        let mut notification = Notification {
+            diff: Some(json!({
+                "ext_auto_renew": { "old": 1, "new": 0 },
+                "ext_expiry_at": { "old": "2025-05-01T00:00:00Z", "new": "2026-05-01T00:00:00Z" },
+                "metadata": {
+                    "old": { "security_lock": 1, "status": "ACTIVE", "labels": [] },
+                    "new": { "security_lock": 0, "status": "AUCTION", "labels": [] },
+                },
+            })),
+            ..Notification::test("example.com", "change")
+        };
+
+        assert_eq!(
+            change_lines(&notification),
+            vec![
+                "Auto Renew Changed: true => false",
+                "ext_expiry_at: 2025-05-01T00:00:00Z => 2026-05-01T00:00:00Z",
+                "Security Lock Changed: true => false",
+                "Status Changed: ACTIVE => AUCTION",
+            ]
+        );
+
+        // notifications without a diff have nothing to list
+        notification.diff = None;
+        assert!(change_lines(&notification).is_empty());
+    }
+
+    #[async_std::test]
+    async fn test_release() {
+        let state = AppStateInner::test().await;
+
+        let mut notifications = Vec::new();
+        for domain in ["a.com", "b.com"] {
+            notifications.push(
+                Notification::new(
+                    &state,
+                    domain.to_string(),
+                    "porkbun",
+                    "add",
+                    "New domain detected".to_string(),
+                    None,
+                )
+                .await
+                .unwrap(),
+            );
+        }
+
+        // the webhook rejects one of the two
+        let server = StubServer::start(|request, _| match request.json()["domain"].as_str() {
+            Some("b.com") => StubResponse::new(400, ""),
+            _ => StubResponse::new(200, ""),
+        })
+        .await;
+        let channels = Channels::load(&Figment::from(Toml::string(&format!(
+            r#"
+            [channels.automation]
+            type = "webhook"
+            url = "{}"
+            delivery = "daily"
+            "#,
+            server.url
+        ))))
+        .await;
+
+        let now = Utc::now();
+        let ids = notifications
+            .iter()
+            .map(|notification| notification.id)
+            .collect::<Vec<_>>();
+        QueuedNotification::enqueue(&state, "automation", &ids, now)
+            .await
+            .unwrap();
+        channels.release(&state, now).await;
+        assert_eq!(server.requests().len(), 2);
+
+        // only the notification that wasn't delivered is retried
+        assert!(QueuedNotification::find_due(&state, "automation", now)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            QueuedNotification::find_due(&state, "automation", now + RELEASE_RETRY)
+                .await
+                .unwrap(),
+            vec![notifications[1].clone()]
+        );
+    }
+}
@@ -0,0 +1,445 @@
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use async_std::{future::timeout, task};
+use chrono::{DateTime, Utc};
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::server::metrics::record_provider_error;
+
+/// Timeout for a single request, slow endpoints can override it per request
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_ATTEMPTS: u32 = 4;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// Upper bound for backoff and Retry-After waits
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Consecutive failed calls before the circuit opens
+const BREAKER_THRESHOLD: u32 = 5;
+/// How long an open circuit rejects calls before letting one through again
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+/// Shared http layer for provider apis.
+/// Requests time out, transient failures (network errors, 429 & 5xx) are retried with
+/// exponential backoff & jitter honouring Retry-After (or matrix's `retry_after_ms`), and a circuit breaker stops calling
+/// a provider that keeps failing. Every failed request is counted in `dmn_provider_errors_total`.
+/// Notification channels use [`ProviderClient::channel`], which only retries.
+pub struct ProviderClient {
+    provider: &'static str,
+    client: reqwest::Client,
+    /// `None` for notification channels, they are neither counted as provider errors nor
+    /// stopped by a breaker
+    breaker: Option<Mutex<Breaker>>,
+}
+
+#[derive(Default)]
+struct Breaker {
+    failures: u32,
+    opened_at: Option<Instant>,
+    /// A half open circuit lets a single call through, the others wait for its outcome
+    probing: bool,
+}
+
+/// Ends the probe of a half open circuit, also when the call fails permanently or is dropped
+struct Probe<'a>(&'a Mutex<Breaker>);
+
+impl Drop for Probe<'_> {
+    fn drop(&mut self) {
+        self.0.lock().unwrap().probing = false;
+    }
+}
+
+enum Attempt<T> {
+    Done(T),
+    Retry(Error, Option<Duration>),
+    Fail(Error),
+}
+
+impl ProviderClient {
+    pub fn new(provider: &'static str) -> Self {
+        Self {
+            provider,
+            client: build_client(),
+            breaker: Some(Mutex::new(Breaker::default())),
+        }
+    }
+
+    /// Client for a notification channel, requests are retried without touching the provider
+    /// metrics or a circuit breaker
+    pub fn channel(name: &'static str) -> Self {
+        Self {
+            provider: name,
+            client: build_client(),
+            breaker: None,
+        }
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    pub fn put(&self, url: &str) -> RequestBuilder {
+        self.client.put(url)
+    }
+
+    /// Send a request built with [`Self::get`], [`Self::post`] or [`Self::put`].
+    /// Responses with other error statuses (401, 404, ...) are returned as is.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        self.execute(|| {
+            let request = request.try_clone();
+
+            async move {
+                let Some(request) = request else {
+                    return Attempt::Fail(Error::msg("Request body can not be retried"));
+                };
+
+                match request.send().await {
+                    Ok(response) if is_transient(response.status()) => {
+                        let status = response.status();
+                        let retry_after = match retry_after(&response) {
+                            Some(retry_after) => Some(retry_after),
+                            None => response
+                                .json::<Value>()
+                                .await
+                                .ok()
+                                .and_then(|body| retry_after_ms(&body)),
+                        };
+                        Attempt::Retry(
+                            Error::msg(format!("Request failed with {}", status)),
+                            retry_after,
+                        )
+                    }
+                    Ok(response) => {
+                        if response.status().is_client_error() {
+                            self.record_error();
+                        }
+                        Attempt::Done(response)
+                    }
+                    Err(e) if is_retryable(&e) => Attempt::Retry(e.into(), None),
+                    Err(e) => Attempt::Fail(e.into()),
+                }
+            }
+        })
+        .await
+    }
+
+    /// Send a request a single time with a short `limit`, without retries or the circuit breaker.
+    /// For probes that have to answer quickly, like the readiness check.
+    pub async fn send_once(
+        &self,
+        request: RequestBuilder,
+        limit: Duration,
+    ) -> Result<Response, Error> {
+        Ok(request.timeout(limit).send().await?)
+    }
+
+    /// [`Self::send_once`] for calls made through a provider sdk
+    pub async fn call_once<T, E>(
+        &self,
+        limit: Duration,
+        call: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, Error>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        match timeout(limit, call).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(Error::msg(format!("{} request timed out", self.provider))),
+        }
+    }
+
+    /// Run a call made through a provider sdk (cloudflare, ...) with a timeout, retries and the
+    /// circuit breaker. `is_transient` decides which errors are worth retrying.
+    pub async fn call<T, E, F, Fut>(
+        &self,
+        is_transient: impl Fn(&E) -> bool,
+        mut f: F,
+    ) -> Result<T, Error>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.execute(|| {
+            let call = f();
+
+            async {
+                match timeout(DEFAULT_TIMEOUT, call).await {
+                    Ok(Ok(value)) => Attempt::Done(value),
+                    Ok(Err(e)) if is_transient(&e) => Attempt::Retry(e.into(), None),
+                    Ok(Err(e)) => Attempt::Fail(e.into()),
+                    Err(_) => Attempt::Retry(Error::msg("Request timed out"), None),
+                }
+            }
+        })
+        .await
+    }
+
+    async fn execute<T, F, Fut>(&self, mut attempt: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Attempt<T>>,
+    {
+        let _probe = self.check_breaker()?;
+
+        let mut tries = 0;
+        loop {
+            tries += 1;
+
+            let attempt = attempt().await;
+            if !matches!(attempt, Attempt::Done(_)) {
+                self.record_error();
+            }
+
+            match attempt {
+                Attempt::Done(value) => {
+                    self.record(true);
+                    return Ok(value);
+                }
+                // not a provider outage (bad credentials, invalid request, ...)
+                Attempt::Fail(e) => return Err(e),
+                Attempt::Retry(e, _) if tries >= MAX_ATTEMPTS => {
+                    self.record(false);
+                    return Err(e.context(format!(
+                        "{} request failed after {} attempts",
+                        self.provider, tries
+                    )));
+                }
+                Attempt::Retry(e, retry_after) => {
+                    let delay = retry_after
+                        .unwrap_or_else(|| backoff(tries))
+                        .min(MAX_BACKOFF);
+                    warn!(
+                        "{} request failed ({}), retrying in {:?} ({}/{})",
+                        self.provider, e, delay, tries, MAX_ATTEMPTS
+                    );
+                    task::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Reject calls while the circuit is open, returns the probe guard when the call is the
+    /// single one let through a half open circuit
+    fn check_breaker(&self) -> Result<Option<Probe<'_>>, Error> {
+        let Some(mutex) = &self.breaker else {
+            return Ok(None);
+        };
+        let mut breaker = mutex.lock().unwrap();
+
+        match breaker.allow(Instant::now()) {
+            Ok(false) => Ok(None),
+            Ok(true) => Ok(Some(Probe(mutex))),
+            Err(wait) => Err(Error::msg(format!(
+                "{} is unavailable after {} failed requests, retrying in {}s",
+                self.provider,
+                breaker.failures,
+                wait.as_secs()
+            ))),
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let Some(breaker) = &self.breaker else {
+            return;
+        };
+
+        if breaker.lock().unwrap().record(success, Instant::now()) {
+            warn!("Circuit breaker for {} opened", self.provider);
+        }
+    }
+
+    fn record_error(&self) {
+        if self.breaker.is_some() {
+            record_provider_error(self.provider);
+        }
+    }
+}
+
+fn build_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .use_rustls_tls()
+        .timeout(DEFAULT_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .expect("Failed to build http client")
+}
+
+impl Breaker {
+    /// Whether a call may go through and if it is the probe of a half open circuit,
+    /// or how long the circuit stays open
+    fn allow(&mut self, now: Instant) -> Result<bool, Duration> {
+        let Some(opened_at) = self.opened_at else {
+            return Ok(false);
+        };
+
+        let open = now.duration_since(opened_at);
+        if open < BREAKER_COOLDOWN {
+            return Err(BREAKER_COOLDOWN - open);
+        }
+
+        // half open, a single call probes whether the provider is back
+        if self.probing {
+            return Err(Duration::ZERO);
+        }
+        self.probing = true;
+
+        Ok(true)
+    }
+
+    /// Returns true when the circuit opened because of this failure
+    fn record(&mut self, success: bool, now: Instant) -> bool {
+        if success {
+            *self = Breaker::default();
+            return false;
+        }
+
+        self.failures += 1;
+        self.probing = false;
+        if self.failures < BREAKER_THRESHOLD {
+            return false;
+        }
+
+        // a failing probe restarts the cooldown
+        let opened = self.opened_at.is_none();
+        self.opened_at = Some(now);
+        opened
+    }
+}
+
+/// Network failures that may go away, errors building the request (invalid url, ...) never will
+fn is_retryable(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect()
+}
+
+pub fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Exponential backoff with jitter, between half and the full delay for this attempt
+pub fn backoff(tries: u32) -> Duration {
+    let delay = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(tries - 1))
+        .min(MAX_BACKOFF);
+    let half = delay.as_millis() as u64 / 2;
+
+    Duration::from_millis(half + rand::random_range(0..=half))
+}
+
+/// Parse the Retry-After header, either in seconds or as an http date
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    parse_retry_after(
+        response.headers().get(RETRY_AFTER)?.to_str().ok()?,
+        Utc::now(),
+    )
+}
+
+/// Matrix homeservers send the wait of a rate limited request (M_LIMIT_EXCEEDED) in the body
+fn retry_after_ms(body: &Value) -> Option<Duration> {
+    body["retry_after_ms"].as_u64().map(Duration::from_millis)
+}
+
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let at = DateTime::parse_from_rfc2822(value).ok()?.to_utc();
+    Some((at - now).to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+
+        for _ in 1..BREAKER_THRESHOLD {
+            assert!(!breaker.record(false, now));
+        }
+        assert_eq!(breaker.allow(now), Ok(false));
+
+        // opens on the threshold and rejects calls during the cooldown
+        assert!(breaker.record(false, now));
+        assert_eq!(
+            breaker.allow(now + Duration::from_secs(60)),
+            Err(BREAKER_COOLDOWN - Duration::from_secs(60))
+        );
+
+        // half open lets a single probe through
+        let later = now + BREAKER_COOLDOWN;
+        assert_eq!(breaker.allow(later), Ok(true));
+        assert!(breaker.allow(later).is_err());
+
+        // a failing probe opens the circuit again
+        assert!(!breaker.record(false, later));
+        assert!(breaker.allow(later + Duration::from_secs(1)).is_err());
+
+        // a successful probe closes it
+        let later = later + BREAKER_COOLDOWN;
+        assert_eq!(breaker.allow(later), Ok(true));
+        breaker.record(true, later);
+        assert_eq!(breaker.allow(later), Ok(false));
+        assert_eq!(breaker.allow(later), Ok(false));
+    }
+
+    #[async_std::test]
+    async fn test_is_retryable() {
+        assert!(is_transient(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_transient(StatusCode::BAD_GATEWAY));
+        assert!(!is_transient(StatusCode::UNAUTHORIZED));
+        assert!(!is_transient(StatusCode::NOT_FOUND));
+
+        let client = reqwest::Client::new();
+
+        // nothing listens on port 1
+        let refused = client.get("http://127.0.0.1:1/").send().await.unwrap_err();
+        assert!(is_retryable(&refused));
+
+        let invalid = client.get("http://[::1/").send().await.unwrap_err();
+        assert!(!is_retryable(&invalid));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc3339("2025-04-26T08:00:00Z")
+            .unwrap()
+            .to_utc();
+
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Sat, 26 Apr 2025 08:00:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        // dates in the past mean retry right away
+        assert_eq!(
+            parse_retry_after("Sat, 26 Apr 2025 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+
+        assert_eq!(
+            retry_after_ms(&serde_json::json!({ "errcode": "M_LIMIT_EXCEEDED", "retry_after_ms": 2500 })),
+            Some(Duration::from_millis(2500))
+        );
+        assert_eq!(retry_after_ms(&serde_json::json!({ "errcode": "M_UNKNOWN" })), None);
+    }
+}
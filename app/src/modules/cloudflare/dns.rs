@@ -3,7 +3,7 @@ use tracing::info;
 
 use crate::{modules::DNSService, Error, state::AppState};
 
-use super::CloudflareService;
+use super::{is_transient, CloudflareService};
 
 impl DNSService for CloudflareService {
     async fn ingest_dns_domains(&self, _state: &AppState) -> Result<(), Error> {
@@ -11,9 +11,12 @@ impl DNSService for CloudflareService {
             params: ListZonesParams::default(),
         };
 
-        let zones = self.client.request(&list_zones).await?;
+        let zones = self
+            .http
+            .call(is_transient, || self.client.request(&list_zones))
+            .await?;
 
-        let peek = zones.result[0..2].as_ref();
+        let peek = zones.result.iter().take(2).collect::<Vec<_>>();
         info!("Cloudflare zones: {:?}", peek);
 
         // TODO: Ingest DNS Zone into Database
@@ -7,7 +7,7 @@ use crate::{models::domain::Domain, modules::DomainService, state::AppState, Err
 
 use super::{
     domains_endpoint::{ListDomains, ListDomainsParams},
-    CloudflareService,
+    is_transient, CloudflareService,
 };
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -62,11 +62,14 @@ pub struct CloudflareDomain {
 }
 
 impl DomainService for CloudflareService {
-    async fn ingest_domains(&self, state: &AppState) -> Result<Vec<Domain>, Error> {
+    async fn ingest_domains(&self) -> Result<Vec<Domain>, Error> {
         let accounts = ListAccounts {
             params: Some(ListAccountsParams::default()),
         };
-        let accounts = self.client.request(&accounts).await?;
+        let accounts = self
+            .http
+            .call(is_transient, || self.client.request(&accounts))
+            .await?;
         info!("Cloudflare accounts: {:?}", accounts);
 
         let mut result_domains: Vec<Domain> = Vec::new();
@@ -80,7 +83,10 @@ impl DomainService for CloudflareService {
                     account: account.id,
                 },
             };
-            let domains_result = self.client.request(&domains).await?;
+            let domains_result = self
+                .http
+                .call(is_transient, || self.client.request(&domains))
+                .await?;
 
             info!(
                 "Cloudflare domains result: {:?}",
@@ -123,10 +129,7 @@ impl DomainService for CloudflareService {
                     ext_auto_renew,
                     ext_whois_privacy,
                     Some(metadata),
-                    state,
-                )
-                .await
-                .unwrap();
+                );
 
                 info!("Cloudflare domain ingested: {:?}", domain);
 
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use cloudflare::{
     endpoints::zones::zone::{ListZones, ListZonesParams, Zone},
     framework::{auth::Credentials, client::async_api::Client, response::ApiFailure},
@@ -6,6 +8,8 @@ use figment::{providers::Env, Figment};
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
+use crate::{modules::client::ProviderClient, Error};
+
 pub mod dns;
 pub mod domains;
 pub mod domains_endpoint;
@@ -20,6 +24,7 @@ pub struct CloudflareConfig {
 pub struct CloudflareService {
     pub config: CloudflareConfig,
     pub client: Client,
+    pub http: ProviderClient,
 }
 
 impl CloudflareService {
@@ -41,7 +46,11 @@ impl CloudflareService {
         let cf_env = cloudflare::framework::Environment::Production;
         let client = Client::new(credentials, cf_config, cf_env).unwrap();
 
-        Self { config, client }
+        Self {
+            config,
+            client,
+            http: ProviderClient::new("cloudflare"),
+        }
     }
 
     pub async fn try_init(provider: &impl figment::Provider) -> Option<Self> {
@@ -50,6 +59,11 @@ impl CloudflareService {
             .merge(provider)
             .extract::<CloudflareConfig>();
         if let Ok(config) = config {
+            if config.api_key.is_none() && config.global_api_key.is_none() {
+                info!("Cloudflare not configured");
+                return None;
+            }
+
             let service = Self::new(config);
             info!("Cloudflare config verified");
             service.get_zones().await.ok()?;
@@ -61,16 +75,40 @@ impl CloudflareService {
         }
     }
 
-    pub async fn get_zones(&self) -> Result<Vec<Zone>, ApiFailure> {
+    pub async fn get_zones(&self) -> Result<Vec<Zone>, Error> {
         let list_zones = ListZones {
             params: ListZonesParams::default(),
         };
 
-        let zones = self.client.request(&list_zones).await?;
+        let zones = self
+            .http
+            .call(is_transient, || self.client.request(&list_zones))
+            .await?;
 
         let peek = zones.result.len();
         info!("Cloudflare zones: {:?}", peek);
 
         Ok(zones.result)
     }
+
+    /// List the zones once with a short timeout and no retries, for the readiness check
+    pub async fn check_credentials(&self, limit: Duration) -> Result<(), Error> {
+        let list_zones = ListZones {
+            params: ListZonesParams::default(),
+        };
+
+        self.http
+            .call_once(limit, self.client.request(&list_zones))
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Rate limits and server errors are worth retrying. Invalid responses (malformed,
+/// unauthenticated, ...) won't get better, timeouts are retried by the client itself.
+pub fn is_transient(failure: &ApiFailure) -> bool {
+    match failure {
+        ApiFailure::Error(status, ..) => status.as_u16() == 429 || status.is_server_error(),
+        ApiFailure::Invalid(_) => false,
+    }
 }
@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use async_std::{prelude::FutureExt, task};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{models::notification::Severity, state::AppState, Error};
+
+/// How often the queue is checked for notifications to release
+const RELEASE_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_DIGEST_AT: &str = "09:00";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryMode {
+    #[default]
+    Immediate,
+    /// Every notification of the past hour in one summary, on the hour
+    Hourly,
+    /// Every notification of the past day in one summary, at `digest_at`
+    Daily,
+}
+
+/// Delivery options every channel accepts next to its `type`
+#[derive(Debug, Default, Deserialize)]
+pub struct DeliveryConfig {
+    /// "immediate" (default), "hourly" or "daily"
+    pub delivery: Option<DeliveryMode>,
+    /// Time of the daily digest (default "09:00")
+    pub digest_at: Option<String>,
+    /// "22:00-07:00", non-critical notifications are held back until the quiet hours end
+    pub quiet_hours: Option<String>,
+    /// Timezone of `digest_at` & `quiet_hours` (default "UTC")
+    pub timezone: Option<String>,
+}
+
+/// When a channel delivers its notifications. Critical notifications are always sent right away.
+#[derive(Debug, Clone)]
+pub struct DeliverySchedule {
+    mode: DeliveryMode,
+    digest_at: NaiveTime,
+    quiet_hours: Option<(NaiveTime, NaiveTime)>,
+    timezone: Tz,
+}
+
+impl Default for DeliverySchedule {
+    fn default() -> Self {
+        Self {
+            mode: DeliveryMode::Immediate,
+            digest_at: parse_time(DEFAULT_DIGEST_AT).unwrap(),
+            quiet_hours: None,
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+impl DeliverySchedule {
+    pub fn new(config: DeliveryConfig) -> Result<Self, Error> {
+        let timezone = match &config.timezone {
+            Some(timezone) => timezone
+                .parse::<Tz>()
+                .map_err(|e| Error::msg(format!("Invalid timezone `{}`: {}", timezone, e)))?,
+            None => Tz::UTC,
+        };
+
+        let quiet_hours = match &config.quiet_hours {
+            Some(quiet_hours) => {
+                let (start, end) = quiet_hours.split_once('-').ok_or_else(|| {
+                    Error::msg(format!(
+                        "Invalid quiet hours `{}`, expected \"22:00-07:00\"",
+                        quiet_hours
+                    ))
+                })?;
+                Some((parse_time(start)?, parse_time(end)?))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            mode: config.delivery.unwrap_or_default(),
+            digest_at: parse_time(config.digest_at.as_deref().unwrap_or(DEFAULT_DIGEST_AT))?,
+            quiet_hours,
+            timezone,
+        })
+    }
+
+    /// When a notification produced at `now` should be delivered, `None` to send it right away
+    pub fn release_at(&self, now: DateTime<Utc>, severity: Severity) -> Option<DateTime<Utc>> {
+        if severity == Severity::Critical {
+            return None;
+        }
+
+        let local = now.with_timezone(&self.timezone);
+        let release = match self.mode {
+            DeliveryMode::Immediate => now,
+            DeliveryMode::Hourly => {
+                let hour = NaiveTime::from_hms_opt(local.hour(), 0, 0).unwrap();
+                self.at(local.date_naive(), hour) + TimeDelta::hours(1)
+            }
+            DeliveryMode::Daily => {
+                let today = self.at(local.date_naive(), self.digest_at);
+                if today > now {
+                    today
+                } else {
+                    self.at(local.date_naive() + TimeDelta::days(1), self.digest_at)
+                }
+            }
+        };
+
+        let release = self.after_quiet_hours(release);
+        (release > now).then_some(release)
+    }
+
+    /// Move `at` to the end of the quiet hours when it falls within them
+    fn after_quiet_hours(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let Some((start, end)) = self.quiet_hours else {
+            return at;
+        };
+
+        let local = at.with_timezone(&self.timezone);
+        let time = local.time();
+        let quiet = if start <= end {
+            time >= start && time < end
+        } else {
+            // quiet hours over midnight
+            time >= start || time < end
+        };
+
+        if !quiet {
+            return at;
+        }
+
+        if time < end {
+            self.at(local.date_naive(), end)
+        } else {
+            self.at(local.date_naive() + TimeDelta::days(1), end)
+        }
+    }
+
+    /// `time` on `date` in the timezone of the schedule
+    fn at(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        let local = date.and_time(time);
+
+        match self.timezone.from_local_datetime(&local).earliest() {
+            Some(at) => at.to_utc(),
+            // skipped by a daylight saving change, use the time an hour later
+            None => self
+                .timezone
+                .from_local_datetime(&(local + TimeDelta::hours(1)))
+                .earliest()
+                .map(|at| at.to_utc())
+                .unwrap_or_else(|| local.and_utc()),
+        }
+    }
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, Error> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M")
+        .map_err(|e| Error::msg(format!("Invalid time `{}`: {}", time, e)))
+}
+
+/// Deliver queued notifications once their digest is due or the quiet hours are over
+pub async fn release_queued(state: &AppState) {
+    loop {
+        {
+            let Some(_job) = state.shutdown.job().await else {
+                break;
+            };
+
+            state.channels.release(state, Utc::now()).await;
+        }
+
+        task::sleep(RELEASE_INTERVAL)
+            .race(state.shutdown.requested())
+            .await;
+
+        if state.shutdown.is_requested() {
+            break;
+        }
+    }
+
+    info!("Notification release stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(at: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(at).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_release_at() {
+        let schedule = DeliverySchedule::new(DeliveryConfig {
+            delivery: Some(DeliveryMode::Daily),
+            digest_at: Some("08:00".to_string()),
+            quiet_hours: Some("22:00-07:00".to_string()),
+            timezone: Some("Europe/Amsterdam".to_string()),
+        })
+        .unwrap();
+
+        // 10:00 in amsterdam (summer time), the digest goes out tomorrow at 08:00
+        assert_eq!(
+            schedule.release_at(utc("2025-04-26T08:00:00Z"), Severity::Normal),
+            Some(utc("2025-04-27T06:00:00Z"))
+        );
+        assert_eq!(
+            schedule.release_at(utc("2025-04-26T08:00:00Z"), Severity::Critical),
+            None
+        );
+
+        let schedule = DeliverySchedule::new(DeliveryConfig {
+            quiet_hours: Some("22:00-07:00".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // held back until the quiet hours end, sent right away otherwise
+        assert_eq!(
+            schedule.release_at(utc("2025-04-26T23:30:00Z"), Severity::High),
+            Some(utc("2025-04-27T07:00:00Z"))
+        );
+        assert_eq!(
+            schedule.release_at(utc("2025-04-26T12:00:00Z"), Severity::Normal),
+            None
+        );
+    }
+}
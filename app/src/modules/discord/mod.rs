@@ -0,0 +1,284 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::notification::Notification,
+    modules::{
+        channels::{change_lines, event_title, group_by_event, group_by_provider},
+        client::ProviderClient,
+        NotificationChannel,
+    },
+    state::AppState,
+    util::color::{provider_name, provider_rgb},
+    Error,
+};
+
+// https://discord.com/developers/docs/resources/message#embed-object-embed-limits
+const MAX_EMBEDS: usize = 10;
+const MAX_FIELDS: usize = 25;
+const MAX_FIELD_VALUE: usize = 1024;
+const MAX_MESSAGE_CHARS: usize = 6000;
+/// Embed colour for domains without a known provider
+const DEFAULT_COLOR: u32 = 0x6B7280;
+
+#[derive(Debug, Deserialize)]
+pub struct DiscordConfig {
+    /// Incoming webhook url, `https://discord.com/api/webhooks/<id>/<token>`
+    pub url: String,
+    /// Overrides the name of the webhook
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookMessage<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<&'a str>,
+    embeds: Vec<Embed>,
+}
+
+#[derive(Debug, Serialize)]
+struct Embed {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    color: u32,
+    author: EmbedAuthor,
+    fields: Vec<EmbedField>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedAuthor {
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedField {
+    name: String,
+    value: String,
+    inline: bool,
+}
+
+impl Embed {
+    /// Characters counted towards the message limit
+    fn len(&self) -> usize {
+        self.title.chars().count()
+            + self.author.name.chars().count()
+            + self.fields.iter().map(EmbedField::len).sum::<usize>()
+    }
+}
+
+impl EmbedField {
+    fn len(&self) -> usize {
+        self.name.chars().count() + self.value.chars().count()
+    }
+}
+
+/// Posts notifications to a discord channel as embeds, one per event & provider
+pub struct DiscordChannel {
+    url: String,
+    username: Option<String>,
+    client: ProviderClient,
+}
+
+impl DiscordChannel {
+    pub fn new(config: DiscordConfig) -> Self {
+        Self {
+            url: config.url,
+            username: config.username,
+            client: ProviderClient::channel("discord"),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for DiscordChannel {
+    async fn send_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), Error> {
+        let dashboard = state.http.base_url().map(|base_url| format!("{}/", base_url));
+
+        for embeds in batch(build_embeds(notifications, dashboard.as_deref())) {
+            let message = WebhookMessage {
+                username: self.username.as_deref(),
+                embeds,
+            };
+
+            let response = self
+                .client
+                .send(self.client.post(&self.url).json(&message))
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(Error::msg(format!(
+                    "Discord responded with {}: {}",
+                    response.status(),
+                    response.text().await.unwrap_or_default()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn build_embeds(notifications: &[Notification], dashboard: Option<&str>) -> Vec<Embed> {
+    let mut embeds = Vec::new();
+
+    for (event, group) in group_by_event(notifications) {
+        let title = event_title(event, group.len());
+
+        for (provider, group) in group_by_provider(&group) {
+            let provider = provider.unwrap_or("unknown");
+            let color = provider_rgb(provider)
+                .map(|(r, g, b)| u32::from_be_bytes([0, r, g, b]))
+                .unwrap_or(DEFAULT_COLOR);
+
+            let embed = || Embed {
+                title: title.clone(),
+                url: dashboard.map(str::to_string),
+                color,
+                author: EmbedAuthor {
+                    name: provider_name(provider).to_string(),
+                },
+                fields: Vec::new(),
+            };
+
+            // a new embed once the current one is out of fields or characters
+            let mut current = embed();
+            for notification in group {
+                let field = field(notification);
+                let full = current.fields.len() >= MAX_FIELDS
+                    || current.len() + field.len() > MAX_MESSAGE_CHARS;
+                if full && !current.fields.is_empty() {
+                    embeds.push(std::mem::replace(&mut current, embed()));
+                }
+                current.fields.push(field);
+            }
+            embeds.push(current);
+        }
+    }
+
+    embeds
+}
+
+fn field(notification: &Notification) -> EmbedField {
+    let changes = change_lines(notification);
+    let value = if changes.is_empty() {
+        notification.message.clone()
+    } else {
+        changes
+            .iter()
+            .map(|line| format!("• {}", line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    EmbedField {
+        name: notification.domain.clone(),
+        value: truncate(&value, MAX_FIELD_VALUE),
+        inline: false,
+    }
+}
+
+/// Pack embeds into as few messages as the discord limits allow
+fn batch(embeds: Vec<Embed>) -> Vec<Vec<Embed>> {
+    let mut messages: Vec<Vec<Embed>> = Vec::new();
+    let mut chars = 0;
+
+    for embed in embeds {
+        let len = embed.len();
+        match messages.last_mut() {
+            Some(message) if message.len() < MAX_EMBEDS && chars + len <= MAX_MESSAGE_CHARS => {
+                chars += len;
+                message.push(embed);
+            }
+            _ => {
+                chars = len;
+                messages.push(vec![embed]);
+            }
+        }
+    }
+
+    messages
+}
+
+fn truncate(value: &str, max: usize) -> String {
+    if value.chars().count() <= max {
+        return value.to_string();
+    }
+
+    let mut value = value.chars().take(max - 1).collect::<String>();
+    value.push('…');
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_batch_embeds() {
+        let notifications = (0..60)
+            .map(|i| Notification {
+                id: i,
+                provider: Some(if i % 2 == 0 { "porkbun" } else { "cloudflare" }.to_string()),
+                diff: Some(json!({ "ext_auto_renew": { "old": true, "new": false } })),
+                ..Notification::test(&format!("domain{}.com", i), "change")
+            })
+            .collect::<Vec<_>>();
+
+        let embeds = build_embeds(&notifications, Some("https://dmn.example.com/"));
+        // 30 per provider, split at 25 fields
+        assert_eq!(embeds.len(), 4);
+        assert_eq!(embeds[0].color, 0xEF7878);
+        assert_eq!(embeds[0].author.name, "Porkbun");
+        assert_eq!(embeds[0].fields.len(), 25);
+        assert_eq!(embeds[1].fields.len(), 5);
+        assert_eq!(embeds[2].color, 0xF48120);
+
+        // everything fits in a single message
+        assert_eq!(batch(embeds).len(), 1);
+    }
+
+    #[test]
+    fn test_embed_chars() {
+        let notifications = (0..25)
+            .map(|i| Notification {
+                message: "x".repeat(1000),
+                ..Notification::test(&format!("domain{}.com", i), "expiry")
+            })
+            .collect::<Vec<_>>();
+
+        // 25 fields of 1000 characters don't fit in a single embed
+        let embeds = build_embeds(&notifications, None);
+        assert_eq!(embeds.len(), 5);
+        assert!(embeds
+            .iter()
+            .all(|embed| embed.fields.len() == 5 && embed.len() <= MAX_MESSAGE_CHARS));
+
+        assert_eq!(batch(embeds).len(), 5);
+    }
+
+    #[test]
+    fn test_embed_multibyte_chars() {
+        let notifications = (0..25)
+            .map(|i| Notification {
+                message: "ü🙂".repeat(500),
+                ..Notification::test(&format!("bücher{}.de", i), "expiry")
+            })
+            .collect::<Vec<_>>();
+
+        // discord counts characters, not the 3000 bytes of each message
+        let embeds = build_embeds(&notifications, None);
+        assert_eq!(embeds.len(), 5);
+        assert!(embeds
+            .iter()
+            .all(|embed| embed.fields.len() == 5 && embed.len() <= MAX_MESSAGE_CHARS));
+
+        assert_eq!(batch(embeds).len(), 5);
+    }
+}
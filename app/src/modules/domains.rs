@@ -1,12 +1,13 @@
 use std::collections::{HashMap, HashSet};
 
-use serde_json::Value;
+use serde_json::{json, Value};
 use tracing::info;
 
 use crate::{
     models::{domain::Domain, notification::Notification},
-    modules::DomainService,
+    modules::{reminders::remind_expiring, DomainService},
     state::AppState,
+    util::value::bool_from_value,
 };
 
 const IGNORED_DIFF_KEYS: &[&str] = &["created_at", "updated_at"];
@@ -19,38 +20,56 @@ pub async fn diff_provider(
 ) -> Result<Vec<Notification>, anyhow::Error> {
     let pre = Domain::find_by_provider(state, provider).await?;
 
-    let post = provider_domains.ingest_domains(state).await?;
+    let ingested = provider_domains.ingest_domains().await?;
 
     let mut notifications = Vec::new();
 
+    // the domains, deletions & their notifications are committed together, a sync that fails or
+    // is interrupted half way doesn't leave the domains table partially updated
+    let mut tx = state.database.pool.begin().await?;
+
+    let mut post = Vec::with_capacity(ingested.len());
+    for domain in &ingested {
+        post.push(domain.upsert(&mut tx).await?);
+    }
+
     // diff deletions
     let (additions, deletions, changes) = diff_changes(&pre, &post).await?;
 
     for deletion in deletions {
         info!("Domain deleted: {}", deletion);
 
-        Domain::delete_by_name(state, provider, &deletion).await?;
+        Domain::delete_by_name(&mut tx, provider, &deletion).await?;
 
         // TODO: notify user the domain was deleted
-        notifications.push(Notification::new(state, deletion, "delete", "Domain deleted".to_string()).await?);
+        notifications.push(Notification::insert(&mut tx, deletion, provider, "delete", "Domain deleted".to_string(), None).await?);
     }
 
     for addition in additions {
         info!("New domain detected: {}", addition.name);
         // TODO: notify user the domain was added
-        notifications.push(Notification::new(state, addition.name, "add", "New domain detected".to_string()).await?);
+        notifications.push(Notification::insert(&mut tx, addition.name, provider, "add", "New domain detected".to_string(), None).await?);
     }
 
     for change in changes {
         // println!("Change detected for domain: {} - {:?}", change.0.name, change.1);
         // TODO: notify user the domain was changed
 
+        let diff = diff_to_json(&change.1);
         let human = diff_to_human(change.0.name.clone(), change.1);
         info!("{}", human);
 
-        notifications.push(Notification::new(state, change.0.name, "change", human).await?);
+        notifications.push(Notification::insert(&mut tx, change.0.name, provider, "change", human, Some(diff)).await?);
+    }
+
+    tx.commit().await?;
+
+    for notification in &notifications {
+        state.notification_bus.publish(notification);
     }
 
+    notifications.extend(remind_expiring(state, &post).await?);
+
     Ok(notifications)
 }
 
@@ -179,14 +198,11 @@ fn diff_to_human(
     human.trim_matches('\n').to_string()
 }
 
-fn bool_from_value(value: &serde_json::Value) -> bool {
-    if value.is_boolean() {
-        value.as_bool().unwrap()
-    } else if value.is_number() {
-        value.as_i64().unwrap() != 0
-    } else if value.is_string() {
-        value.as_str().unwrap().to_lowercase() == "true"
-    } else {
-        false
-    }
+/// Structured version of a change, `{"field": {"old": .., "new": ..}}`
+fn diff_to_json(change: &HashMap<String, (serde_json::Value, serde_json::Value)>) -> Value {
+    change
+        .iter()
+        .map(|(key, (pre, post))| (key.clone(), json!({ "old": pre, "new": post })))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
 }
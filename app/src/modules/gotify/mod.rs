@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    models::notification::{Notification, Severity},
+    modules::{
+        channels::{event_title, group_by_event, render_list},
+        client::ProviderClient,
+        NotificationChannel,
+    },
+    state::AppState,
+    Error,
+};
+
+/// Highest priority gotify knows
+const MAX_PRIORITY: u8 = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct GotifyConfig {
+    /// Base url of the gotify server
+    pub url: String,
+    /// Application token
+    pub token: String,
+    /// Gotify priority (0-10) per severity, overrides the defaults. Higher values are capped at 10.
+    #[serde(default)]
+    pub priorities: BTreeMap<Severity, u8>,
+}
+
+/// Pushes notifications to a gotify application, one message per event
+pub struct GotifyChannel {
+    url: String,
+    token: String,
+    priorities: BTreeMap<Severity, u8>,
+    client: ProviderClient,
+}
+
+impl GotifyChannel {
+    pub fn new(config: GotifyConfig) -> Self {
+        Self {
+            url: format!("{}/message", config.url.trim_end_matches('/')),
+            token: config.token,
+            priorities: config.priorities,
+            client: ProviderClient::channel("gotify"),
+        }
+    }
+
+    fn priority(&self, severity: Severity) -> u8 {
+        self.priorities
+            .get(&severity)
+            .copied()
+            .unwrap_or(match severity {
+                Severity::Low => 2,
+                Severity::Normal => 5,
+                Severity::High => 8,
+                Severity::Critical => MAX_PRIORITY,
+            })
+            .min(MAX_PRIORITY)
+    }
+
+    /// One message for the notifications of an event
+    fn message(&self, event: &str, group: &[&Notification], dashboard: Option<&str>) -> Value {
+        let severity = group
+            .iter()
+            .map(|notification| notification.severity())
+            .max()
+            .unwrap_or(Severity::Normal);
+
+        let mut message = json!({
+            "title": event_title(event, group.len()),
+            "message": render_list(group),
+            "priority": self.priority(severity),
+            "extras": {
+                "client::display": { "contentType": "text/plain" },
+            },
+        });
+        if let Some(dashboard) = dashboard {
+            message["extras"]["client::notification"] = json!({ "click": { "url": dashboard } });
+        }
+
+        message
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for GotifyChannel {
+    async fn send_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), Error> {
+        let dashboard = state
+            .http
+            .base_url()
+            .map(|base_url| format!("{}/", base_url));
+
+        for (event, group) in group_by_event(notifications) {
+            let message = self.message(event, &group, dashboard.as_deref());
+
+            let response = self
+                .client
+                .send(
+                    self.client
+                        .post(&self.url)
+                        .header("X-Gotify-Key", &self.token)
+                        .json(&message),
+                )
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(Error::msg(format!(
+                    "Gotify responded with {}",
+                    response.status()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(priorities: &[(Severity, u8)]) -> GotifyChannel {
+        GotifyChannel::new(GotifyConfig {
+            url: "https://gotify.example.com/".to_string(),
+            token: "token".to_string(),
+            priorities: priorities.iter().copied().collect(),
+        })
+    }
+
+    #[test]
+    fn test_priority() {
+        let channel = channel(&[(Severity::Low, 0), (Severity::Critical, 42)]);
+        assert_eq!(channel.url, "https://gotify.example.com/message");
+
+        assert_eq!(channel.priority(Severity::Low), 0);
+        assert_eq!(channel.priority(Severity::Normal), 5);
+        assert_eq!(channel.priority(Severity::High), 8);
+        // out of range values are capped
+        assert_eq!(channel.priority(Severity::Critical), MAX_PRIORITY);
+
+        // the most severe notification of an event decides
+        let lost_lock = Notification {
+            diff: Some(json!({
+                "metadata": { "old": { "security_lock": 1 }, "new": { "security_lock": 0 } }
+            })),
+            ..Notification::test("a.com", "change")
+        };
+        let change = Notification::test("b.com", "change");
+        let message = channel.message(
+            "change",
+            &[&change, &lost_lock],
+            Some("https://dmn.example.com/"),
+        );
+        assert_eq!(message["priority"], 10);
+        assert_eq!(message["title"], "Domains Changed");
+        assert_eq!(
+            message["extras"]["client::notification"]["click"]["url"],
+            "https://dmn.example.com/"
+        );
+
+        assert_eq!(channel.message("change", &[&change], None)["priority"], 5);
+    }
+}
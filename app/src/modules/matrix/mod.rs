@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use maud::html;
+use reqwest::{RequestBuilder, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    models::notification::Notification,
+    modules::{
+        channels::{change_lines, event_title, group_by_event},
+        client::ProviderClient,
+        NotificationChannel,
+    },
+    state::AppState,
+    Error,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct MatrixConfig {
+    /// Base url of the homeserver, e.g. "https://matrix.example.com"
+    pub homeserver: String,
+    /// Access token of the account posting the messages, it has to be joined to the room
+    pub access_token: String,
+    /// Room id ("!abc:example.com") or alias ("#alerts:example.com")
+    pub room: String,
+}
+
+/// Posts notifications to an (unencrypted) matrix room, one message per event
+pub struct MatrixChannel {
+    homeserver: Url,
+    access_token: String,
+    room: String,
+    client: ProviderClient,
+}
+
+impl MatrixChannel {
+    pub fn new(config: MatrixConfig) -> Result<Self, Error> {
+        let homeserver = Url::parse(&config.homeserver)?;
+        if homeserver.cannot_be_a_base() {
+            return Err(Error::msg(format!(
+                "Invalid homeserver {}",
+                config.homeserver
+            )));
+        }
+
+        Ok(Self {
+            homeserver,
+            access_token: config.access_token,
+            room: config.room,
+            client: ProviderClient::channel("matrix"),
+        })
+    }
+
+    /// Client-server api url, segments are percent encoded
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .expect("homeserver is a base url")
+            .pop_if_empty()
+            .extend(["_matrix", "client", "v3"])
+            .extend(segments);
+        url
+    }
+
+    /// The room id, resolving the room alias if one was configured
+    async fn room_id(&self) -> Result<String, Error> {
+        if !self.room.starts_with('#') {
+            return Ok(self.room.clone());
+        }
+
+        let response = self
+            .request(self.client.get(self.url(&["directory", "room", &self.room]).as_str()))
+            .await?;
+
+        response["room_id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::msg(format!("Room alias {} did not resolve", self.room)))
+    }
+
+    /// Send an authenticated request, the client waits out rate limits & retries server errors
+    async fn request(&self, request: RequestBuilder) -> Result<Value, Error> {
+        let response = self
+            .client
+            .send(request.bearer_auth(&self.access_token))
+            .await?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.json().await?);
+        }
+
+        let body = response.json::<Value>().await.unwrap_or_default();
+        Err(Error::msg(format!(
+            "Homeserver responded with {}: {}",
+            status,
+            body["error"]
+                .as_str()
+                .unwrap_or_else(|| status.canonical_reason().unwrap_or_default())
+        )))
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for MatrixChannel {
+    async fn send_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), Error> {
+        let room_id = self.room_id().await?;
+        let dashboard = state
+            .http
+            .base_url()
+            .map(|base_url| format!("{}/", base_url));
+
+        for (event, group) in group_by_event(notifications) {
+            let content = json!({
+                "msgtype": "m.notice",
+                "body": render_markdown(event, &group, dashboard.as_deref()),
+                "format": "org.matrix.custom.html",
+                "formatted_body": render_html(event, &group, dashboard.as_deref()),
+            });
+
+            // the same transaction id on retries keeps the homeserver from posting twice
+            let txn_id = format!("dmn-{}", hex::encode(rand::random::<[u8; 8]>()));
+            let url = self.url(&["rooms", &room_id, "send", "m.room.message", &txn_id]);
+
+            self.request(self.client.put(url.as_str()).json(&content))
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Plain body for clients without html support
+fn render_markdown(
+    event: &str,
+    notifications: &[&Notification],
+    dashboard: Option<&str>,
+) -> String {
+    let mut body = format!("**{}**\n", event_title(event, notifications.len()));
+
+    for notification in notifications {
+        body += &match (notification.event.as_str(), &notification.provider) {
+            ("add" | "delete" | "change", Some(provider)) => {
+                format!("\n- `{}` ({})", notification.domain, provider)
+            }
+            _ => format!("\n- {}", notification.message),
+        };
+
+        for line in change_lines(notification) {
+            body += &format!("\n  - {}", line);
+        }
+    }
+
+    if let Some(dashboard) = dashboard {
+        body += &format!("\n\n[Open dashboard]({})", dashboard);
+    }
+
+    body
+}
+
+fn render_html(event: &str, notifications: &[&Notification], dashboard: Option<&str>) -> String {
+    html! {
+        strong { (event_title(event, notifications.len())) }
+        ul {
+            @for notification in notifications {
+                li {
+                    @match (notification.event.as_str(), &notification.provider) {
+                        ("add" | "delete" | "change", Some(provider)) => {
+                            code { (notification.domain) } " (" (provider) ")"
+                        }
+                        _ => (notification.message),
+                    }
+                    @let changes = change_lines(notification);
+                    @if !changes.is_empty() {
+                        ul {
+                            @for line in changes {
+                                li { (line) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        @if let Some(dashboard) = dashboard {
+            a href=(dashboard) { "Open dashboard" }
+        }
+    }
+    .into_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        state::AppStateInner,
+        util::stub::{StubResponse, StubServer},
+    };
+
+    #[test]
+    fn test_render() {
+        let change = Notification {
+            diff: Some(json!({ "ext_auto_renew": { "old": true, "new": false } })),
+            ..Notification::test("example.com", "change")
+        };
+
+        assert_eq!(
+            render_markdown("change", &[&change], None),
+            "**Domain Changed**\n\n- `example.com` (porkbun)\n  - Auto Renew Changed: true => false"
+        );
+        assert_eq!(
+            render_html("change", &[&change], Some("https://dmn.example.com/")),
+            "<strong>Domain Changed</strong><ul><li><code>example.com</code> (porkbun)\
+             <ul><li>Auto Renew Changed: true =&gt; false</li></ul></li></ul>\
+             <a href=\"https://dmn.example.com/\">Open dashboard</a>"
+        );
+
+        // reminders are shown by their message, escaped
+        let reminder = Notification {
+            message: "<b>example.com</b> expires today".to_string(),
+            ..Notification::test("example.com", "expiry")
+        };
+        assert_eq!(
+            render_html("expiry", &[&reminder], None),
+            "<strong>Domain Expiring</strong><ul><li>&lt;b&gt;example.com&lt;/b&gt; expires today</li></ul>"
+        );
+    }
+
+    #[async_std::test]
+    async fn test_send_notifications() {
+        let state = AppStateInner::test().await;
+
+        // rate limited once while resolving the alias
+        let homeserver =
+            StubServer::start(
+                |request, received| match (request.method.as_str(), received) {
+                    ("GET", 0) => StubResponse::new(
+                        429,
+                        r#"{"errcode": "M_LIMIT_EXCEEDED", "retry_after_ms": 10}"#,
+                    ),
+                    ("GET", _) => StubResponse::new(200, r#"{"room_id": "!room:example.com"}"#),
+                    _ => StubResponse::new(200, r#"{"event_id": "$event"}"#),
+                },
+            )
+            .await;
+
+        let channel = MatrixChannel::new(MatrixConfig {
+            homeserver: homeserver.url.clone(),
+            access_token: "token".to_string(),
+            room: "#alerts:example.com".to_string(),
+        })
+        .unwrap();
+
+        let notifications = [
+            Notification::test("a.com", "add"),
+            Notification::test("b.com", "delete"),
+        ];
+        channel
+            .send_notifications(&state, &notifications)
+            .await
+            .unwrap();
+
+        let requests = homeserver.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests
+            .iter()
+            .all(|request| request.header("Authorization") == Some("Bearer token")));
+
+        assert_eq!(
+            requests[1].path,
+            "/_matrix/client/v3/directory/room/%23alerts:example.com"
+        );
+
+        // one message per event, sent to the resolved room
+        let prefix = "/_matrix/client/v3/rooms/!room:example.com/send/m.room.message/dmn-";
+        assert_eq!(requests[2].method, "PUT");
+        assert!(requests[2].path.starts_with(prefix));
+        assert_ne!(requests[2].path, requests[3].path);
+        assert_eq!(
+            requests[2].json()["formatted_body"],
+            render_html("delete", &[&notifications[1]], None)
+        );
+        assert_eq!(
+            requests[3].json()["body"],
+            render_markdown("add", &[&notifications[0]], None)
+        );
+    }
+}
@@ -1,13 +1,32 @@
-use crate::{models::domain::Domain, state::AppState, Error};
+use async_trait::async_trait;
 
+use crate::{
+    models::{domain::Domain, notification::Notification},
+    state::AppState,
+    Error,
+};
+
+pub mod channels;
+pub mod client;
 pub mod cloudflare;
+pub mod digest;
+pub mod discord;
 pub mod porkbun;
 pub mod whois;
 pub mod domains;
+pub mod gotify;
+pub mod matrix;
 pub mod ntfy;
+pub mod pushover;
+pub mod reminders;
+pub mod routing;
+pub mod slack;
+pub mod smtp;
+pub mod webhook;
 
 pub trait DomainService {
-    async fn ingest_domains(&self, state: &AppState) -> Result<Vec<Domain>, Error>;
+    /// Fetch the domains of the provider, [`diff_provider`](domains::diff_provider) stores them
+    async fn ingest_domains(&self) -> Result<Vec<Domain>, Error>;
 
     async fn ingest_domain_tld_prices_if_enabled(&self, state: &AppState) -> Result<(), Error>;
 }
@@ -15,3 +34,27 @@ pub trait DomainService {
 pub trait DNSService {
     async fn ingest_dns_domains(&self, state: &AppState) -> Result<(), Error>;
 }
+
+/// A place notifications are delivered to (ntfy, webhook, ...), configured under `[channels.<name>]`
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    /// Deliver the notifications produced by a single sync
+    async fn send_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), Error>;
+
+    /// Deliver queued notifications, returns the ids that were delivered. Channels that send
+    /// them as a single summary deliver all or nothing, channels that deliver every
+    /// notification on its own (webhook) report the ones that went through.
+    async fn release_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<Vec<i64>, Error> {
+        self.send_notifications(state, notifications).await?;
+
+        Ok(notifications.iter().map(|notification| notification.id).collect())
+    }
+}
@@ -1,11 +1,18 @@
-use std::collections::HashMap;
-
+use async_trait::async_trait;
 use figment::{providers::Env, Figment};
 use ntfy::prelude::*;
 use serde::Deserialize;
 use tracing::{info, warn};
 
-use crate::models::notification::Notification;
+use crate::{
+    models::notification::{Notification, EVENT_EXPIRY, EVENT_EXPIRY_URGENT},
+    modules::{
+        channels::{event_title, group_by_event},
+        NotificationChannel,
+    },
+    state::AppState,
+    Error,
+};
 
 pub struct NtfyService {
     pub dispatcher: Dispatcher<Async>,
@@ -51,26 +58,19 @@ impl NtfyService {
         }
     }
 
-    pub async fn send_notifications(&self, notifications: Vec<Notification>) -> Result<(), Error> {
-        let mut notifications_by_topic = HashMap::new();
-
-        for notification in notifications {
-            notifications_by_topic
-                .entry(notification.event.clone())
-                .or_insert(Vec::new())
-                .push(notification);
-        }
+}
 
-        for (topic, notifications) in notifications_by_topic {
-            let plural = if notifications.len() > 1 { "s" } else { "" };
-            let topic_name = match topic.as_str() {
-                "add" => format!("New Domain{}", plural),
-                "delete" => format!("Domain{} Deleted", plural),
-                "change" => format!("Domain{} Changed", plural),
-                _ => "Unknown".to_string(),
-            };
+#[async_trait]
+impl NotificationChannel for NtfyService {
+    async fn send_notifications(
+        &self,
+        _state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), Error> {
+        for (topic, notifications) in group_by_event(notifications) {
+            let topic_name = event_title(topic, notifications.len());
 
-            let message: String = if topic.as_str() == "change" {
+            let message: String = if topic == "change" {
                 format!(
                     "{}\n\n{}",
                     notifications.iter().map(|n| format!("*{}*", n.domain)).collect::<Vec<String>>().join(", "),
@@ -80,7 +80,13 @@ impl NtfyService {
                     .collect::<Vec<String>>()
                     .join("\n\n")
                 )
-            } else if ["add", "delete"].contains(&topic.as_str()) {
+            } else if [EVENT_EXPIRY, EVENT_EXPIRY_URGENT].contains(&topic) {
+                notifications
+                    .iter()
+                    .map(|n| format!("- {}", n.message))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            } else if ["add", "delete"].contains(&topic) {
                 // just show list of domains escaped with `
                 notifications
                     .iter()
@@ -96,9 +102,16 @@ impl NtfyService {
                     .join("\n")
             };
 
+            let priority = if topic == EVENT_EXPIRY_URGENT {
+                Priority::High
+            } else {
+                Priority::Default
+            };
+
             let payload = Payload::new(&self.topic)
                 .message(message)
                 .title(topic_name)
+                .priority(priority)
                 .markdown(true);
 
             self.dispatcher.send(&payload).await?;
@@ -51,7 +51,7 @@ struct ListAllResponse {
 
 /// Based on https://porkbun.com/api/json/v3/documentation#
 impl DomainService for PorkbunService {
-    async fn ingest_domains(&self, state: &AppState) -> Result<Vec<Domain>, Error> {
+    async fn ingest_domains(&self) -> Result<Vec<Domain>, Error> {
         let api_key = self
             .config
             .api_key
@@ -62,18 +62,17 @@ impl DomainService for PorkbunService {
             .secret_key
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("Missing secret_key"))?;
-        let client = reqwest::Client::new();
         let req_body = ListAllRequest {
             apikey: api_key,
             secretapikey: secret_key,
             start: Some(0),
             includeLabels: None,
         };
-        let response = client
+        let request = self
+            .client
             .post("https://api.porkbun.com/api/json/v3/domain/listAll")
-            .json(&req_body)
-            .send()
-            .await?;
+            .json(&req_body);
+        let response = self.client.send(request).await?;
         let status = response.status();
         let text = response.text().await?;
         if !status.is_success() {
@@ -131,10 +130,7 @@ impl DomainService for PorkbunService {
                 ext_auto_renew,
                 ext_whois_privacy,
                 Some(metadata),
-                &state,
-            )
-            .await
-            .unwrap();
+            );
 
             info!("Porkbun domain ingested: {:?}", domain);
 
@@ -149,7 +145,7 @@ impl DomainService for PorkbunService {
     async fn ingest_domain_tld_prices_if_enabled(&self, state: &AppState) -> Result<(), Error> {
         if let Some(porkbun_pricing_config) = &self.config.pricing {
             if porkbun_pricing_config.enabled {
-                ingest_domain_tld_prices(state).await
+                ingest_domain_tld_prices(&self.client, state).await
             } else {
                 Ok(())
             }
@@ -175,6 +171,6 @@ mod tests {
         dotenvy::dotenv().ok();
         let state = Arc::new(AppStateInner::init(true).await);
 
-        let domains = state.porkbun.as_ref().unwrap().ingest_domains(&state).await.unwrap();
+        let domains = state.porkbun.as_ref().unwrap().ingest_domains().await.unwrap();
     }
 }
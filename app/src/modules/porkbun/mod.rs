@@ -1,10 +1,14 @@
+use std::time::Duration;
+
 use anyhow::Error;
 use figment::{providers::Env, Figment};
 use pricing::PorkbunPricingConfig;
-use reqwest;
+use reqwest::{RequestBuilder, Response};
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
+use crate::modules::client::ProviderClient;
+
 pub mod domains;
 pub mod pricing;
 
@@ -17,6 +21,7 @@ pub struct PorkbunConfig {
 
 pub struct PorkbunService {
     pub config: PorkbunConfig,
+    pub client: ProviderClient,
 }
 
 #[derive(Serialize)]
@@ -33,7 +38,10 @@ pub struct PingResponse {
 
 impl PorkbunService {
     pub fn new(config: PorkbunConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            client: ProviderClient::new("porkbun"),
+        }
     }
 
     pub async fn try_init(provider: &impl figment::Provider) -> Option<Self> {
@@ -61,6 +69,19 @@ impl PorkbunService {
     }
 
     pub async fn ping(&self) -> Result<String, Error> {
+        let response = self.client.send(self.ping_request()?).await?;
+
+        parse_ping(response).await
+    }
+
+    /// Ping once with a short timeout and no retries, for the readiness check
+    pub async fn check_credentials(&self, limit: Duration) -> Result<(), Error> {
+        let response = self.client.send_once(self.ping_request()?, limit).await?;
+
+        parse_ping(response).await.map(|_| ())
+    }
+
+    fn ping_request(&self) -> Result<RequestBuilder, Error> {
         let api_key = self
             .config
             .api_key
@@ -71,25 +92,26 @@ impl PorkbunService {
             .secret_key
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("Missing secret_key"))?;
-        let client = reqwest::Client::new();
         let req_body = PingRequest {
             apikey: api_key,
             secretapikey: secret_key,
         };
-        let response = client
+        Ok(self
+            .client
             .post("https://api.porkbun.com/api/json/v3/ping")
-            .json(&req_body)
-            .send()
-            .await?;
-        let status = response.status();
-        let text = response.text().await?;
-        if !status.is_success() {
-            return Err(anyhow::anyhow!("Ping failed: {}", text));
-        }
-        let resp: PingResponse = serde_json::from_str(&text)?;
-        if resp.status != "SUCCESS" {
-            return Err(anyhow::anyhow!("Ping error: {}", resp.status));
-        }
-        Ok(resp.yourIp.unwrap_or_default())
+            .json(&req_body))
+    }
+}
+
+async fn parse_ping(response: Response) -> Result<String, Error> {
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("Ping failed: {}", text));
+    }
+    let resp: PingResponse = serde_json::from_str(&text)?;
+    if resp.status != "SUCCESS" {
+        return Err(anyhow::anyhow!("Ping error: {}", resp.status));
     }
+    Ok(resp.yourIp.unwrap_or_default())
 }
@@ -1,9 +1,15 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
-use crate::{models::domain_tld_price::DomainTLDPrice, state::AppState, Error};
+use crate::{
+    models::domain_tld_price::DomainTLDPrice, modules::client::ProviderClient, state::AppState,
+    Error,
+};
+
+/// The pricing endpoint is slow, it can take up to 30 seconds to respond
+const PRICING_TIMEOUT: Duration = Duration::from_secs(90);
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PorkbunPricingConfig {
@@ -28,18 +34,16 @@ pub struct PorkbunTLDPriceResponse {
     pub pricing: HashMap<String, PorkbunTLDPrice>,
 }
 
-pub async fn ingest_domain_tld_prices(state: &AppState) -> Result<(), Error> {
-    // TODO: Implement
-    // get request to https://api.porkbun.com/api/json/v3/pricing/get
-    // long lasting (takes up to 30seconds)
-    // rustls
+pub async fn ingest_domain_tld_prices(
+    client: &ProviderClient,
+    state: &AppState,
+) -> Result<(), Error> {
     info!("Ingesting domain tld prices");
-    let client = reqwest::Client::builder().use_rustls_tls().build()?;
 
-    let response = client
+    let request = client
         .get("https://api.porkbun.com/api/json/v3/pricing/get")
-        .send()
-        .await?;
+        .timeout(PRICING_TIMEOUT);
+    let response = client.send(request).await?;
 
     let body = response.text().await?;
 
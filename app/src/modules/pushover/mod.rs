@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::{
+    models::notification::{Notification, Severity},
+    modules::{
+        channels::{event_title, group_by_event, render_list},
+        client::ProviderClient,
+        NotificationChannel,
+    },
+    state::AppState,
+    Error,
+};
+
+const API_URL: &str = "https://api.pushover.net/1/messages.json";
+const MAX_MESSAGE: usize = 1024;
+/// Pushover priority that repeats until acknowledged
+const EMERGENCY: i8 = 2;
+/// Seconds between emergency repeats (minimum 30)
+const EMERGENCY_RETRY: u32 = 60;
+/// Seconds emergency notifications keep repeating
+const EMERGENCY_EXPIRE: u32 = 3600;
+
+#[derive(Debug, Deserialize)]
+pub struct PushoverConfig {
+    /// Application api token
+    pub token: String,
+    /// User or group key
+    pub user: String,
+    /// Only push to this device
+    pub device: Option<String>,
+    /// Pushover priority (-2 to 2) per severity, overrides the defaults
+    #[serde(default)]
+    pub priorities: BTreeMap<Severity, i8>,
+}
+
+/// Pushes notifications through pushover, one message per event
+pub struct PushoverChannel {
+    token: String,
+    user: String,
+    device: Option<String>,
+    priorities: BTreeMap<Severity, i8>,
+    client: ProviderClient,
+}
+
+impl PushoverChannel {
+    pub fn new(config: PushoverConfig) -> Self {
+        Self {
+            token: config.token,
+            user: config.user,
+            device: config.device,
+            priorities: config.priorities,
+            client: ProviderClient::channel("pushover"),
+        }
+    }
+
+    fn priority(&self, severity: Severity) -> i8 {
+        self.priorities
+            .get(&severity)
+            .copied()
+            .unwrap_or(match severity {
+                Severity::Low => -1,
+                Severity::Normal => 0,
+                Severity::High => 1,
+                Severity::Critical => EMERGENCY,
+            })
+            .clamp(-2, EMERGENCY)
+    }
+
+    /// Form fields of the message for the notifications of an event
+    fn form(
+        &self,
+        event: &str,
+        group: &[&Notification],
+        dashboard: Option<&str>,
+    ) -> Vec<(&'static str, String)> {
+        let severity = group
+            .iter()
+            .map(|notification| notification.severity())
+            .max()
+            .unwrap_or(Severity::Normal);
+        let priority = self.priority(severity);
+
+        let mut message = render_list(group);
+        if message.chars().count() > MAX_MESSAGE {
+            message = message.chars().take(MAX_MESSAGE - 1).collect::<String>() + "…";
+        }
+
+        let mut form = vec![
+            ("token", self.token.clone()),
+            ("user", self.user.clone()),
+            ("title", event_title(event, group.len())),
+            ("message", message),
+            ("priority", priority.to_string()),
+        ];
+        // emergency notifications repeat until acknowledged, pushover requires both
+        if priority == EMERGENCY {
+            form.push(("retry", EMERGENCY_RETRY.to_string()));
+            form.push(("expire", EMERGENCY_EXPIRE.to_string()));
+        }
+        if let Some(device) = &self.device {
+            form.push(("device", device.clone()));
+        }
+        if let Some(dashboard) = dashboard {
+            form.push(("url", dashboard.to_string()));
+            form.push(("url_title", "Open dashboard".to_string()));
+        }
+
+        form
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for PushoverChannel {
+    async fn send_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), Error> {
+        let dashboard = state
+            .http
+            .base_url()
+            .map(|base_url| format!("{}/", base_url));
+
+        for (event, group) in group_by_event(notifications) {
+            let form = self.form(event, &group, dashboard.as_deref());
+
+            let response = self
+                .client
+                .send(self.client.post(API_URL).form(&form))
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(Error::msg(format!(
+                    "Pushover responded with {}: {}",
+                    response.status(),
+                    response.text().await.unwrap_or_default()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(priorities: &[(Severity, i8)]) -> PushoverChannel {
+        PushoverChannel::new(PushoverConfig {
+            token: "token".to_string(),
+            user: "user".to_string(),
+            device: None,
+            priorities: priorities.iter().copied().collect(),
+        })
+    }
+
+    fn field<'a>(form: &'a [(&str, String)], name: &str) -> Option<&'a str> {
+        form.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn test_priority() {
+        let channel = channel(&[(Severity::Low, -5), (Severity::High, 2)]);
+
+        // out of range values are clamped
+        assert_eq!(channel.priority(Severity::Low), -2);
+        assert_eq!(channel.priority(Severity::Normal), 0);
+        assert_eq!(channel.priority(Severity::High), EMERGENCY);
+        assert_eq!(channel.priority(Severity::Critical), EMERGENCY);
+    }
+
+    #[test]
+    fn test_form() {
+        let channel = channel(&[]);
+
+        let add = Notification::test("a.com", "add");
+        let form = channel.form("add", &[&add], Some("https://dmn.example.com/"));
+        assert_eq!(field(&form, "priority"), Some("-1"));
+        assert_eq!(field(&form, "retry"), None);
+        assert_eq!(field(&form, "expire"), None);
+        assert_eq!(field(&form, "url"), Some("https://dmn.example.com/"));
+
+        // emergency notifications repeat every minute for an hour
+        let delete = Notification::test("b.com", "delete");
+        let form = channel.form("delete", &[&delete], None);
+        assert_eq!(field(&form, "priority"), Some("2"));
+        assert_eq!(field(&form, "retry"), Some("60"));
+        assert_eq!(field(&form, "expire"), Some("3600"));
+        assert_eq!(field(&form, "url"), None);
+    }
+}
@@ -0,0 +1,197 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    models::{
+        domain::Domain,
+        domain_reminder::DomainReminder,
+        notification::{Notification, EVENT_EXPIRY, EVENT_EXPIRY_URGENT},
+    },
+    state::AppState,
+    Error,
+};
+
+const DEFAULT_THRESHOLDS: &[i64] = &[90, 30, 7, 1];
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ReminderConfig {
+    pub enabled: Option<bool>, // default true
+    /// Days before expiry a reminder is sent (default [90, 30, 7, 1])
+    pub thresholds: Option<Vec<i64>>,
+}
+
+impl ReminderConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    pub fn thresholds(&self) -> Vec<i64> {
+        let mut thresholds = self
+            .thresholds
+            .clone()
+            .unwrap_or_else(|| DEFAULT_THRESHOLDS.to_vec());
+
+        thresholds.retain(|threshold| {
+            if *threshold < 0 {
+                warn!("Ignoring negative reminder threshold {}", threshold);
+            }
+            *threshold >= 0
+        });
+        thresholds.sort_unstable_by(|a, b| b.cmp(a));
+        thresholds.dedup();
+
+        thresholds
+    }
+}
+
+/// Create expiry reminders for `domains` that passed a threshold.
+/// Each threshold fires once per expiry date, when several are passed at once (e.g. a newly
+/// added domain expiring in 5 days) only the closest one is sent.
+pub async fn remind_expiring(
+    state: &AppState,
+    domains: &[Domain],
+) -> Result<Vec<Notification>, Error> {
+    if !state.reminders.enabled() {
+        return Ok(Vec::new());
+    }
+
+    let thresholds = state.reminders.thresholds();
+    let now = Utc::now();
+    let mut notifications = Vec::new();
+
+    for domain in domains {
+        let Some(expiry_at) = domain.ext_expiry_at else {
+            continue;
+        };
+        if expiry_at < now {
+            continue;
+        }
+
+        let days_left = (expiry_at - now).num_days();
+        let fired = DomainReminder::find_fired(state, domain, expiry_at).await?;
+
+        let due = due_thresholds(&thresholds, days_left, &fired);
+        if due.is_empty() {
+            continue;
+        }
+
+        let (event, message) = reminder(domain, expiry_at, days_left);
+        info!("Expiry reminder: {}", message);
+
+        // the thresholds are only marked as fired together with the reminder
+        let mut tx = state.database.pool.begin().await?;
+        DomainReminder::record(&mut tx, domain, expiry_at, &due).await?;
+        let notification = Notification::insert(
+            &mut tx,
+            domain.name.clone(),
+            &domain.provider,
+            event,
+            message,
+            None,
+        )
+        .await?;
+        tx.commit().await?;
+
+        state.notification_bus.publish(&notification);
+        notifications.push(notification);
+    }
+
+    Ok(notifications)
+}
+
+/// Thresholds passed since the last reminder, `thresholds` sorted from far to close
+fn due_thresholds(thresholds: &[i64], days_left: i64, fired: &[i64]) -> Vec<i64> {
+    thresholds
+        .iter()
+        .copied()
+        .filter(|threshold| days_left <= *threshold && fired.iter().all(|fired| threshold < fired))
+        .collect()
+}
+
+/// Event & message of the reminder for `domain`
+fn reminder(domain: &Domain, expiry_at: DateTime<Utc>, days_left: i64) -> (&'static str, String) {
+    let expires = match days_left {
+        0 => "today".to_string(),
+        1 => "in 1 day".to_string(),
+        days => format!("in {} days", days),
+    };
+    let mut message = format!(
+        "{} expires {} ({})",
+        domain.name,
+        expires,
+        expiry_at.format("%Y-%m-%d")
+    );
+
+    // escalate when nothing will renew the domain for us
+    if domain.ext_auto_renew == Some(false) {
+        message += ", auto renew is disabled so it has to be renewed manually";
+        (EVENT_EXPIRY_URGENT, message)
+    } else {
+        (EVENT_EXPIRY, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domain(auto_renew: Option<bool>) -> Domain {
+        Domain {
+            name: "example.com".to_string(),
+            provider: "porkbun".to_string(),
+            external_id: None,
+            ext_expiry_at: None,
+            ext_registered_at: None,
+            ext_auto_renew: auto_renew,
+            ext_whois_privacy: None,
+            metadata: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_due_thresholds() {
+        let thresholds = [90, 30, 7, 1];
+
+        assert_eq!(due_thresholds(&thresholds, 120, &[]), Vec::<i64>::new());
+        assert_eq!(due_thresholds(&thresholds, 60, &[]), vec![90]);
+        // several passed at once
+        assert_eq!(due_thresholds(&thresholds, 5, &[]), vec![90, 30, 7]);
+        // fired thresholds & the ones before them don't fire again
+        assert_eq!(due_thresholds(&thresholds, 5, &[30]), vec![7]);
+        assert_eq!(due_thresholds(&thresholds, 20, &[30]), Vec::<i64>::new());
+        assert_eq!(due_thresholds(&thresholds, 0, &[7]), vec![1]);
+    }
+
+    #[test]
+    fn test_reminder() {
+        let expiry_at = DateTime::parse_from_rfc3339("2025-05-01T12:00:00Z")
+            .unwrap()
+            .to_utc();
+
+        assert_eq!(
+            reminder(&domain(Some(true)), expiry_at, 7),
+            (
+                EVENT_EXPIRY,
+                "example.com expires in 7 days (2025-05-01)".to_string()
+            )
+        );
+        assert_eq!(
+            reminder(&domain(None), expiry_at, 1),
+            (
+                EVENT_EXPIRY,
+                "example.com expires in 1 day (2025-05-01)".to_string()
+            )
+        );
+
+        // escalates when auto renew is disabled
+        let (event, message) = reminder(&domain(Some(false)), expiry_at, 0);
+        assert_eq!(event, EVENT_EXPIRY_URGENT);
+        assert_eq!(
+            message,
+            "example.com expires today (2025-05-01), auto renew is disabled so it has to be renewed manually"
+        );
+    }
+}
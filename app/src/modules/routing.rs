@@ -0,0 +1,190 @@
+use figment::Figment;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::models::notification::{Notification, Severity};
+
+/// A `[[routes]]` rule, every set condition has to match.
+/// Lists match when any of their entries does, empty lists match everything.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Route {
+    /// Shown by `dmn notify route`
+    pub name: Option<String>,
+    /// "add", "delete", "change", "expiry" or "expiry_urgent"
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub providers: Vec<String>,
+    /// Domain globs, `*` matches any number of characters ("*.example.com", "shop-*")
+    #[serde(default)]
+    pub domains: Vec<String>,
+    /// Top level domains without the leading dot ("dev", "co.uk")
+    #[serde(default)]
+    pub tlds: Vec<String>,
+    /// Minimum severity (low, normal, high, critical)
+    pub severity: Option<Severity>,
+    /// Only matches changes that touch nothing but these fields ("ext_auto_renew", "metadata.status")
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// Channels the notification is sent to, an empty list drops it
+    pub channels: Vec<String>,
+}
+
+impl Route {
+    pub fn matches(&self, notification: &Notification) -> bool {
+        let domain = notification.domain.to_lowercase();
+
+        (self.events.is_empty() || self.events.contains(&notification.event))
+            && (self.providers.is_empty()
+                || notification
+                    .provider
+                    .as_ref()
+                    .is_some_and(|provider| self.providers.contains(provider)))
+            && (self.domains.is_empty()
+                || self
+                    .domains
+                    .iter()
+                    .any(|pattern| glob_match(&pattern.to_lowercase(), &domain)))
+            && (self.tlds.is_empty()
+                || self.tlds.iter().any(|tld| {
+                    domain.ends_with(&format!(".{}", tld.trim_start_matches('.').to_lowercase()))
+                }))
+            && self
+                .severity
+                .is_none_or(|severity| notification.severity() >= severity)
+            && (self.fields.is_empty() || {
+                let changed = notification.changed_fields();
+                !changed.is_empty() && changed.iter().all(|field| self.fields.contains(field))
+            })
+    }
+
+    /// The name of the route, or its position in the config
+    pub fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("#{}", index + 1))
+    }
+}
+
+/// Decides which channels a notification goes to, the first matching route wins.
+/// Notifications no route matches go to every channel.
+#[derive(Debug, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn load(config_file: &Figment) -> Self {
+        if config_file.find_value("routes").is_err() {
+            return Self::default();
+        }
+
+        let routes = config_file
+            .extract_inner::<Vec<Route>>("routes")
+            .unwrap_or_else(|error| {
+                warn!("Failed to load routes config: {}", error);
+                Vec::new()
+            });
+
+        Self { routes }
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// The first matching route and its index
+    pub fn route(&self, notification: &Notification) -> Option<(usize, &Route)> {
+        self.routes
+            .iter()
+            .enumerate()
+            .find(|(_, route)| route.matches(notification))
+    }
+
+    /// Whether the notification should be sent to `channel`
+    pub fn allows(&self, notification: &Notification, channel: &str) -> bool {
+        match self.route(notification) {
+            Some((_, route)) => route.channels.iter().any(|name| name == channel),
+            None => true,
+        }
+    }
+}
+
+/// Match `text` against a pattern where `*` matches any number of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` and the text position it was tried at
+    let mut backtrack = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // let the `*` swallow one more character
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, t));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::providers::{Format, Toml};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        let router = Router::load(&Figment::from(Toml::string(
+            r#"
+            [[routes]]
+            events = ["change"]
+            fields = ["metadata.status"]
+            channels = []
+
+            [[routes]]
+            severity = "critical"
+            channels = ["email", "chat"]
+
+            [[routes]]
+            events = ["add"]
+            tlds = ["dev"]
+            channels = ["phone"]
+            "#,
+        )));
+
+        let mut notification = Notification::test("example.dev", "add");
+        assert!(router.allows(&notification, "phone"));
+        assert!(!router.allows(&notification, "email"));
+
+        // no route matches, sent everywhere
+        notification.domain = "example.com".to_string();
+        assert!(router.route(&notification).is_none());
+        assert!(router.allows(&notification, "email"));
+
+        notification.event = "delete".to_string();
+        assert_eq!(router.route(&notification).unwrap().0, 1);
+
+        // status flapping is dropped
+        notification.event = "change".to_string();
+        notification.diff = Some(json!({
+            "metadata": { "old": { "status": "ACTIVE" }, "new": { "status": "PENDING" } }
+        }));
+        assert_eq!(router.route(&notification).unwrap().1.channels.len(), 0);
+
+        assert!(glob_match("*.example.com", "shop.example.com"));
+        assert!(glob_match("shop-*.com", "shop-eu.com"));
+        assert!(!glob_match("*.example.com", "example.com"));
+    }
+}
@@ -0,0 +1,234 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    models::notification::Notification,
+    modules::{
+        channels::{change_lines, event_title, group_by_event, group_by_provider, summarize},
+        client::ProviderClient,
+        NotificationChannel,
+    },
+    state::AppState,
+    util::color::{provider_hex, provider_name},
+    Error,
+};
+
+/// Blocks per message, counting the headers of every group & the dashboard link
+const MAX_BLOCKS: usize = 50;
+const MAX_TEXT: usize = 3000;
+
+#[derive(Debug, Deserialize)]
+pub struct SlackConfig {
+    /// Incoming webhook url, `https://hooks.slack.com/services/...`
+    pub url: String,
+}
+
+/// Posts notifications to a slack channel as Block Kit messages, colour coded by provider
+pub struct SlackChannel {
+    url: String,
+    client: ProviderClient,
+}
+
+impl SlackChannel {
+    pub fn new(config: SlackConfig) -> Self {
+        Self {
+            url: config.url,
+            client: ProviderClient::channel("slack"),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SlackChannel {
+    async fn send_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), Error> {
+        let dashboard = state.http.base_url().map(|base_url| format!("{}/", base_url));
+
+        for message in build_messages(notifications, dashboard.as_deref()) {
+            let response = self
+                .client
+                .send(self.client.post(&self.url).json(&message))
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(Error::msg(format!(
+                    "Slack responded with {}: {}",
+                    response.status(),
+                    response.text().await.unwrap_or_default()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// One attachment per event & provider, the attachment colour shows the provider.
+/// Groups that don't fit in a message continue in the next one under the same header.
+fn build_messages(notifications: &[Notification], dashboard: Option<&str>) -> Vec<Value> {
+    let budget = MAX_BLOCKS - usize::from(dashboard.is_some());
+
+    let mut messages = Vec::new();
+    let mut batch: Vec<Notification> = Vec::new();
+    let mut attachments = Vec::new();
+    let mut blocks = 0;
+
+    for (event, group) in group_by_event(notifications) {
+        let title = event_title(event, group.len());
+
+        for (provider, group) in group_by_provider(&group) {
+            let provider = provider.unwrap_or("unknown");
+            let mut remaining = group.as_slice();
+
+            while !remaining.is_empty() {
+                // room for the header & at least one domain
+                if budget - blocks < 2 {
+                    messages.push(build_message(&batch, attachments, dashboard));
+                    batch = Vec::new();
+                    attachments = Vec::new();
+                    blocks = 0;
+                }
+
+                let (part, rest) = remaining.split_at(remaining.len().min(budget - blocks - 1));
+                remaining = rest;
+
+                let mut attachment_blocks = vec![json!({
+                    "type": "section",
+                    "text": {
+                        "type": "mrkdwn",
+                        "text": format!("*{}* · {}", title, provider_name(provider)),
+                    },
+                })];
+                attachment_blocks.extend(part.iter().map(|n| section(n)));
+
+                blocks += attachment_blocks.len();
+                batch.extend(part.iter().map(|n| (*n).clone()));
+                attachments.push(json!({
+                    "color": provider_hex(provider),
+                    "blocks": attachment_blocks,
+                }));
+            }
+        }
+    }
+
+    if !attachments.is_empty() {
+        messages.push(build_message(&batch, attachments, dashboard));
+    }
+
+    messages
+}
+
+fn build_message(
+    notifications: &[Notification],
+    attachments: Vec<Value>,
+    dashboard: Option<&str>,
+) -> Value {
+    let mut blocks = Vec::new();
+    if let Some(dashboard) = dashboard {
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{
+                "type": "mrkdwn",
+                "text": format!("<{}|Open the dmn dashboard>", dashboard),
+            }],
+        }));
+    }
+
+    json!({
+        // shown in notifications & clients without block support
+        "text": format!("dmn: {}", summarize(notifications)),
+        "blocks": blocks,
+        "attachments": attachments,
+    })
+}
+
+fn section(notification: &Notification) -> Value {
+    let changes = change_lines(notification);
+    let details = if changes.is_empty() {
+        escape(&notification.message)
+    } else {
+        changes
+            .iter()
+            .map(|line| format!("• {}", escape(line)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    let mut text = format!("*{}*\n{}", escape(&notification.domain), details);
+    if text.chars().count() > MAX_TEXT {
+        text = text.chars().take(MAX_TEXT - 1).collect::<String>() + "…";
+    }
+
+    json!({
+        "type": "section",
+        "text": { "type": "mrkdwn", "text": text },
+    })
+}
+
+/// Slack only requires escaping these three in mrkdwn
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(message: &Value) -> usize {
+        message["blocks"].as_array().unwrap().len()
+            + message["attachments"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|attachment| attachment["blocks"].as_array().unwrap().len())
+                .sum::<usize>()
+    }
+
+    #[test]
+    fn test_build_messages() {
+        let notifications = (0..60)
+            .map(|i| Notification {
+                id: i,
+                provider: Some(if i % 3 == 0 { "porkbun" } else { "cloudflare" }.to_string()),
+                ..Notification::test(
+                    &format!("domain{}.com", i),
+                    if i % 2 == 0 { "add" } else { "delete" },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let messages = build_messages(&notifications, Some("https://dmn.example.com/"));
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(|message| blocks(message) <= MAX_BLOCKS));
+        // the dashboard link, 4 group headers & 45 domains fill the first message
+        assert_eq!(blocks(&messages[0]), MAX_BLOCKS);
+        assert_eq!(
+            messages[0]["text"],
+            "dmn: 30 Domains Deleted, 15 New Domains"
+        );
+        assert_eq!(messages[1]["text"], "dmn: 15 New Domains");
+
+        // a group split over two messages keeps its header
+        let header = &messages[1]["attachments"][0]["blocks"][0]["text"]["text"];
+        assert_eq!(header, "*New Domains* · Cloudflare");
+    }
+
+    #[test]
+    fn test_section() {
+        let notification = Notification {
+            message: "<b> & co expires today".to_string(),
+            ..Notification::test("example.com", "expiry")
+        };
+
+        assert_eq!(
+            section(&notification)["text"]["text"],
+            "*example.com*\n&lt;b&gt; &amp; co expires today"
+        );
+    }
+}
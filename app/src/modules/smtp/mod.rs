@@ -0,0 +1,244 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncStd1Executor, AsyncTransport, Message,
+};
+use maud::{html, Markup, DOCTYPE};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    models::notification::Notification,
+    modules::{
+        channels::{change_lines, event_title, group_by_event, summarize},
+        NotificationChannel,
+    },
+    state::AppState,
+    util::color::provider_hex,
+    Error,
+};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection using STARTTLS, port 587 by default
+    #[default]
+    Starttls,
+    /// Implicit TLS, port 465 by default
+    Tls,
+    /// Unencrypted, port 25 by default. Only use this for a relay on the same host
+    None,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// Defaults to the standard port for `security`
+    pub port: Option<u16>,
+    /// "starttls" (default), "tls" or "none"
+    pub security: Option<SmtpSecurity>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender address, e.g. "dmn <dmn@example.com>"
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// Mails the notifications of a sync as a single multipart (plaintext & html) email
+pub struct SmtpChannel {
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    transport: AsyncSmtpTransport<AsyncStd1Executor>,
+}
+
+impl SmtpChannel {
+    pub fn new(config: SmtpConfig) -> Result<Self, Error> {
+        let from = config.from.parse::<Mailbox>()?;
+        let to = config
+            .to
+            .iter()
+            .map(|to| to.parse::<Mailbox>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if to.is_empty() {
+            return Err(Error::msg("No recipients configured"));
+        }
+
+        let mut transport = match config.security.unwrap_or_default() {
+            SmtpSecurity::Starttls => {
+                AsyncSmtpTransport::<AsyncStd1Executor>::starttls_relay(&config.host)?
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<AsyncStd1Executor>::relay(&config.host)?,
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<AsyncStd1Executor>::builder_dangerous(&config.host)
+            }
+        };
+
+        if let Some(port) = config.port {
+            transport = transport.port(port);
+        }
+
+        match (config.username, config.password) {
+            (Some(username), Some(password)) => {
+                transport = transport.credentials(Credentials::new(username, password));
+            }
+            (None, None) => {}
+            _ => return Err(Error::msg("Both username and password are required")),
+        }
+
+        Ok(Self {
+            from,
+            to,
+            transport: transport.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SmtpChannel {
+    async fn send_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), Error> {
+        let base_url = state.http.base_url();
+
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(render_subject(notifications));
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+
+        let message = message.multipart(MultiPart::alternative_plain_html(
+            render_plain(notifications, base_url.as_deref()),
+            render_html(notifications, base_url.as_deref()).into_string(),
+        ))?;
+
+        self.transport.send(message).await?;
+        info!("Mailed {} notifications to {} recipients", notifications.len(), self.to.len());
+
+        Ok(())
+    }
+}
+
+/// "dmn: 1 Domain Deleted, 2 New Domains"
+fn render_subject(notifications: &[Notification]) -> String {
+    format!("dmn: {}", summarize(notifications))
+}
+
+fn render_plain(notifications: &[Notification], base_url: Option<&str>) -> String {
+    let mut body = String::new();
+
+    for (event, group) in group_by_event(notifications) {
+        body += &format!("{}\n\n", event_title(event, group.len()));
+
+        for notification in group {
+            body += &format!("- {}", summary(notification));
+            if let Some(provider) = provider(notification) {
+                body += &format!(" ({})", provider);
+            }
+            body += "\n";
+            for line in change_lines(notification) {
+                body += &format!("    {}\n", line);
+            }
+        }
+
+        body += "\n";
+    }
+
+    if let Some(base_url) = base_url {
+        body += &format!("Dashboard: {}/\n", base_url);
+    }
+
+    body
+}
+
+fn render_html(notifications: &[Notification], base_url: Option<&str>) -> Markup {
+    // mail clients ignore stylesheets, so everything is styled inline
+    html! {
+        (DOCTYPE)
+        html {
+            body style="margin: 0; padding: 24px; background: #f5f5f5; font-family: sans-serif; color: #171717;" {
+                div style="max-width: 600px; margin: 0 auto; padding: 24px; background: #ffffff; border-radius: 8px;" {
+                    @for (event, group) in group_by_event(notifications) {
+                        h2 style="margin: 0 0 12px; font-size: 18px;" {
+                            (event_title(event, group.len()))
+                        }
+                        ul style="margin: 0 0 24px; padding-left: 20px;" {
+                            @for notification in group {
+                                li style="margin-bottom: 8px;" {
+                                    (summary(notification))
+                                    @if let Some(provider) = provider(notification) {
+                                        " "
+                                        span style={ "color: " (provider_hex(provider)) ";" } {
+                                            "(" (provider) ")"
+                                        }
+                                    }
+                                    @let details = change_lines(notification);
+                                    @if !details.is_empty() {
+                                        ul style="margin: 4px 0 0; padding-left: 16px; color: #525252;" {
+                                            @for line in details {
+                                                li { (line) }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    @if let Some(base_url) = base_url {
+                        a href={ (base_url) "/" } style="color: #2563eb;" { "Open dashboard" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// One line per notification, reminders describe themselves
+fn summary(notification: &Notification) -> &str {
+    match notification.event.as_str() {
+        "add" | "delete" | "change" => &notification.domain,
+        _ => &notification.message,
+    }
+}
+
+/// Provider of a domain event, reminders already name the domain
+fn provider(notification: &Notification) -> Option<&str> {
+    match notification.event.as_str() {
+        "add" | "delete" | "change" => notification.provider.as_deref(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_render_plain() {
+        let notifications = vec![
+            Notification::test("a.com", "add"),
+            Notification {
+                diff: Some(json!({ "ext_auto_renew": { "old": true, "new": false } })),
+                ..Notification::test("b.com", "change")
+            },
+            Notification::test("c.com", "add"),
+        ];
+
+        assert_eq!(
+            render_subject(&notifications),
+            "dmn: 1 Domain Changed, 2 New Domains"
+        );
+        assert_eq!(
+            render_plain(&notifications, Some("https://dmn.example.com")),
+            "Domain Changed\n\n- b.com (porkbun)\n    Auto Renew Changed: true => false\n\n\
+             New Domains\n\n- a.com (porkbun)\n- c.com (porkbun)\n\n\
+             Dashboard: https://dmn.example.com/\n"
+        );
+    }
+}
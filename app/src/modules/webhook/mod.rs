@@ -0,0 +1,368 @@
+use std::time::{Duration, Instant};
+
+use async_std::task;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use tracing::{info, warn};
+
+use crate::{
+    models::{delivery::Delivery, notification::Notification},
+    modules::{
+        client::{backoff, is_transient, retry_after},
+        NotificationChannel,
+    },
+    state::AppState,
+    Error,
+};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_TIMEOUT: &str = "10s";
+const DEFAULT_DEADLINE: &str = "2m";
+/// Upper bound for waiting between attempts, including Retry-After
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Signs every request with HMAC-SHA256 of `<timestamp>.<body>`, sent as
+    /// `X-Dmn-Signature: sha256=<hex>` next to `X-Dmn-Timestamp`
+    pub secret: Option<String>,
+    /// Attempts per notification before giving up (default 5)
+    pub max_attempts: Option<u32>,
+    /// Timeout of a single attempt (default "10s")
+    pub timeout: Option<String>,
+    /// Time the deliveries of a sync may take in total, including retries (default "2m").
+    /// Syncs wait for their notifications, a slow endpoint can't hold them up for longer.
+    pub deadline: Option<String>,
+}
+
+/// Body of every webhook request, one per notification
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a> {
+    /// Id of the notification, also sent as `X-Dmn-Delivery`
+    pub id: i64,
+    /// "add", "delete", "change", "expiry" or "expiry_urgent"
+    pub event: &'a str,
+    pub domain: &'a str,
+    pub provider: Option<&'a str>,
+    pub message: &'a str,
+    /// Changed fields as `{"field": {"old": .., "new": ..}}`, only for "change"
+    pub diff: Option<&'a Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> From<&'a Notification> for WebhookPayload<'a> {
+    fn from(notification: &'a Notification) -> Self {
+        Self {
+            id: notification.id,
+            event: &notification.event,
+            domain: &notification.domain,
+            provider: notification.provider.as_deref(),
+            message: &notification.message,
+            diff: notification.diff.as_ref(),
+            created_at: notification.created_at.and_utc(),
+        }
+    }
+}
+
+/// POSTs every notification as json to a url, retrying failed deliveries with backoff
+pub struct WebhookChannel {
+    name: String,
+    url: String,
+    secret: Option<String>,
+    max_attempts: u32,
+    deadline: Duration,
+    client: reqwest::Client,
+}
+
+impl WebhookChannel {
+    pub fn new(name: &str, config: WebhookConfig) -> Result<Self, Error> {
+        let timeout = parse_duration(config.timeout.as_deref().unwrap_or(DEFAULT_TIMEOUT))?;
+        let deadline = parse_duration(config.deadline.as_deref().unwrap_or(DEFAULT_DEADLINE))?;
+
+        let client = reqwest::Client::builder()
+            .use_rustls_tls()
+            .timeout(timeout)
+            .build()?;
+
+        Ok(Self {
+            name: name.to_string(),
+            url: config.url,
+            secret: config.secret,
+            max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1),
+            deadline,
+            client,
+        })
+    }
+
+    /// Deliver a single notification, every attempt is recorded as a [`Delivery`].
+    /// Failed attempts are only retried when the retry starts before `deadline`.
+    async fn deliver(
+        &self,
+        state: &AppState,
+        notification: &Notification,
+        deadline: Instant,
+    ) -> Result<(), Error> {
+        let body = serde_json::to_vec(&WebhookPayload::from(notification))?;
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+
+            let timestamp = Utc::now().timestamp().to_string();
+            let mut request = self
+                .client
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .header("X-Dmn-Event", &notification.event)
+                .header("X-Dmn-Delivery", notification.id.to_string())
+                .header("X-Dmn-Timestamp", &timestamp)
+                .body(body.clone());
+            if let Some(secret) = &self.secret {
+                // the timestamp is signed along, a captured request can't be replayed later on
+                let signed = [timestamp.as_bytes(), b".", &body].concat();
+                request = request.header("X-Dmn-Signature", sign(secret, &signed));
+            }
+
+            let started = Instant::now();
+            let (status, error, retryable, wait) = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status()), None, false, None)
+                }
+                Ok(response) => (
+                    Some(response.status()),
+                    Some(format!("Responded with {}", response.status())),
+                    is_transient(response.status()),
+                    retry_after(&response),
+                ),
+                Err(e) => (None, Some(e.to_string()), true, None),
+            };
+
+            if let Err(e) = Delivery::record(
+                state,
+                &self.name,
+                notification.id,
+                attempt as i64,
+                error.is_none(),
+                status.map(|status| status.as_u16() as i64),
+                error.as_deref(),
+                started.elapsed().as_millis() as i64,
+            )
+            .await
+            {
+                warn!("Failed to record webhook delivery: {}", e);
+            }
+
+            let Some(error) = error else {
+                return Ok(());
+            };
+
+            if !retryable || attempt >= self.max_attempts {
+                return Err(Error::msg(format!(
+                    "Delivery of notification {} failed after {} attempts: {}",
+                    notification.id, attempt, error
+                )));
+            }
+
+            let delay = wait.unwrap_or_else(|| backoff(attempt)).min(MAX_RETRY_DELAY);
+            if Instant::now() + delay >= deadline {
+                return Err(Error::msg(format!(
+                    "Delivery of notification {} failed after {} attempts, out of time to retry: {}",
+                    notification.id, attempt, error
+                )));
+            }
+
+            info!(
+                "Webhook {} delivery of notification {} failed ({}), retrying in {:?}",
+                self.name, notification.id, error, delay
+            );
+            task::sleep(delay).await;
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookChannel {
+    async fn send_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<(), Error> {
+        let delivered = self.release_notifications(state, notifications).await?;
+
+        let failed = notifications
+            .iter()
+            .filter(|notification| !delivered.contains(&notification.id))
+            .map(|notification| notification.id.to_string())
+            .collect::<Vec<_>>();
+        if !failed.is_empty() {
+            return Err(Error::msg(format!(
+                "Failed to deliver notifications {}",
+                failed.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn release_notifications(
+        &self,
+        state: &AppState,
+        notifications: &[Notification],
+    ) -> Result<Vec<i64>, Error> {
+        let deadline = Instant::now() + self.deadline;
+        let mut delivered = Vec::new();
+
+        for notification in notifications {
+            if Instant::now() >= deadline {
+                warn!(
+                    "Webhook {}: out of time, notification {} not delivered",
+                    self.name, notification.id
+                );
+                continue;
+            }
+
+            match self.deliver(state, notification, deadline).await {
+                Ok(()) => delivered.push(notification.id),
+                Err(e) => warn!("Webhook {}: {}", self.name, e),
+            }
+        }
+
+        Ok(delivered)
+    }
+}
+
+fn parse_duration(duration: &str) -> Result<Duration, Error> {
+    humantime::parse_duration(duration)
+        .map_err(|e| Error::msg(format!("Invalid duration `{}`: {}", duration, e)))
+}
+
+/// HMAC-SHA256 of `body` as `sha256=<hex>`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+
+    use super::*;
+    use crate::{
+        models::delivery::DeliveryFilter,
+        state::AppStateInner,
+        util::stub::{StubResponse, StubServer},
+    };
+
+    fn channel(url: &str, deadline: &str) -> WebhookChannel {
+        WebhookChannel::new(
+            "automation",
+            WebhookConfig {
+                url: url.to_string(),
+                secret: Some("randomwebhookvalue".to_string()),
+                max_attempts: Some(3),
+                timeout: None,
+                deadline: Some(deadline.to_string()),
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[async_std::test]
+    async fn test_send_notifications() {
+        let state = AppStateInner::test().await;
+        let notification = Notification::new(
+            &state,
+            "example.com".to_string(),
+            "porkbun",
+            "add",
+            "New domain detected".to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+        let notifications = slice::from_ref(&notification);
+
+        // unavailable at first, delivered by the retry
+        let server = StubServer::start(|_, received| match received {
+            0 => StubResponse::new(503, "").header("Retry-After", "0"),
+            _ => StubResponse::new(200, ""),
+        })
+        .await;
+        channel(&server.url, "2m")
+            .send_notifications(&state, notifications)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].json()["domain"], "example.com");
+        assert_eq!(
+            requests[1].header("X-Dmn-Delivery"),
+            Some(notification.id.to_string().as_str())
+        );
+
+        let timestamp = requests[1].header("X-Dmn-Timestamp").unwrap();
+        let signed = [timestamp.as_bytes(), b".", &requests[1].body].concat();
+        assert_eq!(
+            requests[1].header("X-Dmn-Signature"),
+            Some(sign("randomwebhookvalue", &signed).as_str())
+        );
+
+        // every attempt is recorded, the most recent first
+        let deliveries = Delivery::find_recent(
+            &state,
+            &DeliveryFilter {
+                notification_id: Some(notification.id),
+                ..Default::default()
+            },
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            deliveries
+                .iter()
+                .map(|delivery| (delivery.attempt, delivery.success, delivery.status))
+                .collect::<Vec<_>>(),
+            vec![(2, true, Some(200)), (1, false, Some(503))]
+        );
+        assert_eq!(
+            deliveries[1].error.as_deref(),
+            Some("Responded with 503 Service Unavailable")
+        );
+
+        // client errors aren't retried
+        let server = StubServer::start(|_, _| StubResponse::new(400, "")).await;
+        assert!(channel(&server.url, "2m")
+            .send_notifications(&state, notifications)
+            .await
+            .is_err());
+        assert_eq!(server.requests().len(), 1);
+
+        // neither are failures that can't be retried before the deadline
+        let server = StubServer::start(|_, _| StubResponse::new(503, "")).await;
+        assert!(channel(&server.url, "100ms")
+            .send_notifications(&state, notifications)
+            .await
+            .is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+}
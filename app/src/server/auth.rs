@@ -0,0 +1,133 @@
+use poem::{
+    http::{header::AUTHORIZATION, StatusCode},
+    Error, Request,
+};
+use poem_openapi::{
+    auth::{ApiKey, Bearer},
+    SecurityScheme,
+};
+use tracing::{error, warn};
+
+use crate::{
+    models::api_token::{ApiToken, Scope},
+    state::AppState,
+};
+
+/// Bearer token authentication for the `/api` routes.
+/// Accepts either a token created with `dmn token create` or `DMN_API_SECRET`,
+/// which is treated as an admin token.
+#[derive(SecurityScheme)]
+#[oai(ty = "bearer", checker = "api_checker")]
+pub struct ApiAuth(pub ApiPrincipal);
+
+/// The caller behind an authenticated request
+#[derive(Debug, Clone)]
+pub struct ApiPrincipal {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+impl ApiPrincipal {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&Scope::Admin) || self.scopes.contains(&scope)
+    }
+}
+
+impl ApiAuth {
+    /// Reject the request with a 403 unless the token was granted `scope`
+    pub fn require(&self, scope: Scope) -> Result<(), Error> {
+        if self.0.has_scope(scope) {
+            Ok(())
+        } else {
+            warn!("Token {} is missing scope {}", self.0.name, scope);
+            Err(Error::from_string(
+                format!("Missing scope: {}", scope),
+                StatusCode::FORBIDDEN,
+            ))
+        }
+    }
+}
+
+async fn api_checker(req: &Request, bearer: Bearer) -> Option<ApiPrincipal> {
+    let state = req.data::<AppState>()?;
+
+    authenticate(state, &bearer.token).await
+}
+
+/// Extract the bearer token for routes outside of the OpenAPI service
+pub fn bearer_token(req: &Request) -> Option<&str> {
+    req.headers()
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// Resolve a bearer token to the caller, either `DMN_API_SECRET` or a stored api token
+pub async fn authenticate(state: &AppState, token: &str) -> Option<ApiPrincipal> {
+    if let Some(api) = &state.api {
+        if secure_compare(token, &api.secret) {
+            return Some(ApiPrincipal {
+                name: "DMN_API_SECRET".to_string(),
+                scopes: vec![Scope::Admin],
+            });
+        }
+    }
+
+    let token = match ApiToken::find_by_secret(state, token).await {
+        Ok(token) => token?,
+        Err(e) => {
+            error!("Failed to look up api token: {}", e);
+            return None;
+        }
+    };
+
+    if token.is_expired() {
+        warn!("Expired api token used: {}", token.name);
+        return None;
+    }
+
+    if let Err(e) = token.touch(state).await {
+        warn!("Failed to update last used timestamp for {}: {}", token.name, e);
+    }
+
+    Some(ApiPrincipal {
+        scopes: token.scopes(),
+        name: token.name,
+    })
+}
+
+/// Query string authentication for feeds (calendar, rss, ...).
+/// Calendar clients cannot send headers, so each feed has its own secret
+/// passed as `?token=...`.
+#[derive(SecurityScheme)]
+#[oai(ty = "api_key", key_name = "token", key_in = "query")]
+pub struct FeedAuth(ApiKey);
+
+impl FeedAuth {
+    /// Verify the provided token against the secret configured for a feed.
+    /// Feeds without a configured secret are never served.
+    pub fn verify(&self, feed: &str, secret: Option<&str>) -> Result<(), Error> {
+        let Some(secret) = secret else {
+            warn!("No secret configured for the {} feed, refusing request", feed);
+            return Err(Error::from_status(StatusCode::UNAUTHORIZED));
+        };
+
+        if secure_compare(&self.0.key, secret) {
+            Ok(())
+        } else {
+            Err(Error::from_status(StatusCode::UNAUTHORIZED))
+        }
+    }
+}
+
+/// Compare two secrets without short-circuiting on the first mismatching byte.
+fn secure_compare(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
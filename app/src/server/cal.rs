@@ -1,13 +1,18 @@
 use std::collections::HashMap;
 
-use crate::{models::domain::Domain, server::ApiTags, state::AppState};
+use crate::{
+    models::domain::Domain,
+    server::{auth::FeedAuth, ApiTags},
+    state::AppState,
+};
 use chrono::{DateTime, Duration, DurationRound, Utc};
 use icalendar::{Calendar, Component, Event, EventLike};
-use poem::web::Data;
+use poem::{http::StatusCode, web::Data, Error};
 use poem_openapi::{
     payload::{PlainText},
     ApiResponse, OpenApi, ResponseContent,
 };
+use serde::{Deserialize, Serialize};
 
 #[derive(ResponseContent)]
 enum IcsContent {
@@ -25,17 +30,35 @@ pub struct CalApi;
 
 #[OpenApi]
 impl CalApi {
+    /// Calendar of upcoming domain expiries
+    ///
+    /// Requires the calendar feed secret as `?token=...`
     #[oai(path = "/domains.ics", method = "get", tag = "ApiTags::Calendar")]
-    async fn get_cal(&self, state: Data<&AppState>) -> CalendarResponse {
+    async fn get_cal(
+        &self,
+        state: Data<&AppState>,
+        auth: FeedAuth,
+    ) -> Result<CalendarResponse, Error> {
+        if !state.calendar.enabled.unwrap_or(true) {
+            return Err(Error::from_status(StatusCode::NOT_FOUND));
+        }
+
+        auth.verify("calendar", state.calendar.secret.as_deref())?;
+
         let calendar = generate_calendar(&state).await;
 
-        CalendarResponse::Ok(IcsContent::Calendar(PlainText(calendar.to_string())))
+        Ok(CalendarResponse::Ok(IcsContent::Calendar(PlainText(
+            calendar.to_string(),
+        ))))
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct CalendarConfig {
     pub enabled: Option<bool>,      // default true
     pub round_to_day: Option<bool>, // default true
+    /// Secret required as `?token=...` to read the feed
+    pub secret: Option<String>,
 }
 
 async fn generate_calendar(state: &AppState) -> Calendar {
@@ -1,18 +1,204 @@
+use chrono::Utc;
 use poem::{web::Data, Error};
-use poem_openapi::{payload::Json, OpenApi};
+use poem_openapi::{
+    param::{Path, Query},
+    payload::Json,
+    ApiResponse, Object, OpenApi,
+};
 use reqwest::StatusCode;
 
-use crate::{models::domain::Domain, state::AppState, server::ApiTags};
+use crate::{
+    models::{
+        api_token::Scope,
+        domain::{Domain, DomainCursor, DomainFilter, DomainSort, SortDirection},
+        domain_tld_price::DomainTLDPrice,
+        notification::Notification,
+    },
+    server::{auth::ApiAuth, ApiTags},
+    state::AppState,
+};
 
 pub struct DomainApi;
 
+const MAX_PAGE_SIZE: u32 = 1000;
+
+#[derive(ApiResponse)]
+enum DomainsResponse {
+    /// The (paginated) list of domains
+    #[oai(status = 200)]
+    Ok(
+        Json<Vec<Domain>>,
+        /// Pass as `cursor` to fetch the next page, absent on the last page
+        #[oai(header = "X-Next-Cursor")]
+        Option<String>,
+    ),
+}
+
+/// A domain together with its notification history and tld pricing
+#[derive(Debug, Object)]
+pub struct DomainDetails {
+    pub domain: Domain,
+    pub notifications: Vec<Notification>,
+    pub tld_prices: Vec<DomainTLDPrice>,
+}
+
+impl DomainDetails {
+    async fn load(state: &AppState, domain: Domain) -> Result<Self, Error> {
+        let notifications = Notification::find_by_domain(state, &domain.name)
+            .await
+            .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+        let tld_prices = DomainTLDPrice::find_for_domain(state, &domain.name)
+            .await
+            .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+        Ok(Self {
+            domain,
+            notifications,
+            tld_prices,
+        })
+    }
+}
+
 #[OpenApi]
 impl DomainApi {
+    /// List domains
+    ///
+    /// Without `limit` every matching domain is returned, with `limit` the
+    /// response is paginated and the `X-Next-Cursor` header points to the next page.
+    #[allow(clippy::too_many_arguments)]
     #[oai(path = "/domains", method = "get", tag = "ApiTags::Domains")]
-    async fn get_domains(&self, state: Data<&AppState>) -> Result<Json<Vec<Domain>>, Error> {
-        let domains = Domain::get_all(&state).await.map_err(|x| {
-            poem::error::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
-        })?;
-        Ok(Json(domains))
+    async fn get_domains(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        /// Only domains at this provider
+        provider: Query<Option<String>>,
+        /// Only domains ending in this tld ("dev", "co.uk")
+        tld: Query<Option<String>>,
+        /// Only domains expiring within this duration ("30days", "1y")
+        expiring_within: Query<Option<String>>,
+        auto_renew: Query<Option<bool>>,
+        whois_privacy: Query<Option<bool>>,
+        /// Provider status ("active", "registrationActive", ...)
+        status: Query<Option<String>>,
+        /// Substring of the domain name
+        name: Query<Option<String>>,
+        /// Field to sort by (default: expiry)
+        sort: Query<Option<DomainSort>>,
+        /// Sort direction (default: asc)
+        direction: Query<Option<SortDirection>>,
+        /// Cursor from the `X-Next-Cursor` header of the previous page, with the same sort & direction
+        cursor: Query<Option<String>>,
+        /// Page size (max 1000)
+        limit: Query<Option<u32>>,
+    ) -> Result<DomainsResponse, Error> {
+        auth.require(Scope::ReadDomains)?;
+
+        let expiring_before = match expiring_within.0 {
+            Some(within) => {
+                let within = humantime::parse_duration(&within)
+                    .ok()
+                    .and_then(|within| chrono::Duration::from_std(within).ok())
+                    .ok_or_else(|| {
+                        Error::from_string(
+                            format!("Invalid duration for expiring_within: {}", within),
+                            StatusCode::BAD_REQUEST,
+                        )
+                    })?;
+                Some(Utc::now() + within)
+            }
+            None => None,
+        };
+
+        let filter = DomainFilter {
+            provider: provider.0,
+            tld: tld.0,
+            expiring_before,
+            auto_renew: auto_renew.0,
+            whois_privacy: whois_privacy.0,
+            status: status.0,
+            name: name.0,
+        };
+        let sort = sort.0.unwrap_or_default();
+        let direction = direction.0.unwrap_or_default();
+
+        let cursor = match cursor.0 {
+            Some(cursor) => match DomainCursor::decode(&cursor) {
+                // a cursor is only valid for the sort & direction it was created with
+                Some(cursor) if cursor.sort == sort && cursor.direction == direction => {
+                    Some(cursor)
+                }
+                _ => {
+                    return Err(Error::from_string(
+                        "Invalid cursor",
+                        StatusCode::BAD_REQUEST,
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        let limit = limit.0.map(|limit| limit.clamp(1, MAX_PAGE_SIZE));
+
+        let (domains, next) = Domain::find_filtered(
+            &state,
+            &filter,
+            sort,
+            direction,
+            cursor.as_ref(),
+            limit,
+        )
+        .await
+        .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+        Ok(DomainsResponse::Ok(
+            Json(domains),
+            next.map(|cursor| cursor.encode()),
+        ))
+    }
+
+    /// Get a domain by name, returns every provider that knows the domain
+    #[oai(path = "/domains/:name", method = "get", tag = "ApiTags::Domains")]
+    async fn get_domain_by_name(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        name: Path<String>,
+    ) -> Result<Json<Vec<DomainDetails>>, Error> {
+        auth.require(Scope::ReadDomains)?;
+
+        let domains = Domain::find_by_name(&state, &name)
+            .await
+            .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+        if domains.is_empty() {
+            return Err(Error::from_status(StatusCode::NOT_FOUND));
+        }
+
+        let mut details = Vec::new();
+        for domain in domains {
+            details.push(DomainDetails::load(&state, domain).await?);
+        }
+
+        Ok(Json(details))
+    }
+
+    /// Get a domain at a specific provider
+    #[oai(path = "/domains/:provider/:name", method = "get", tag = "ApiTags::Domains")]
+    async fn get_domain(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        provider: Path<String>,
+        name: Path<String>,
+    ) -> Result<Json<DomainDetails>, Error> {
+        auth.require(Scope::ReadDomains)?;
+
+        let domain = Domain::find_by_provider_and_name(&state, &provider, &name)
+            .await
+            .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?
+            .ok_or_else(|| Error::from_status(StatusCode::NOT_FOUND))?;
+
+        Ok(Json(DomainDetails::load(&state, domain).await?))
     }
 }
@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use poem::{handler, http::StatusCode, web::Data, IntoResponse, Response};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    models::job_run::{JobRun, JobRunFilter, OUTCOME_FAILURE, OUTCOME_SUCCESS},
+    server::{schedule::JobKind, sync::PROVIDERS},
+    state::AppState,
+};
+
+/// How long a credential check is trusted before the provider is asked again
+const CREDENTIALS_TTL: Duration = Duration::from_secs(5 * 60);
+/// Checks run one provider after the other, together they stay below the 5s of `dmn healthcheck`
+const CREDENTIALS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Last credential check per provider, so frequent readiness probes don't hit the provider apis
+static CREDENTIALS: LazyLock<Mutex<HashMap<String, (Instant, bool)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Serialize)]
+struct Readiness {
+    ready: bool,
+    database: bool,
+    providers: Vec<ProviderReadiness>,
+}
+
+#[derive(Debug, Serialize)]
+struct ProviderReadiness {
+    provider: String,
+    credentials_valid: bool,
+    last_successful_sync: Option<DateTime<Utc>>,
+    last_sync_error: Option<String>,
+}
+
+/// Liveness, the process is up and serving requests
+#[handler]
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Readiness, the database is reachable and every configured provider accepts its credentials
+#[handler]
+pub async fn readyz(state: Data<&AppState>) -> Response {
+    let database = match sqlx::query("SELECT 1").execute(&state.database.pool).await {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("Readiness check: database unavailable: {}", e);
+            false
+        }
+    };
+
+    let mut providers = Vec::new();
+    for provider in PROVIDERS {
+        if let Some(credentials_valid) = check_credentials(&state, provider).await {
+            let (last_successful_sync, last_sync_error) = last_sync(&state, provider).await;
+            providers.push(ProviderReadiness {
+                provider: provider.to_string(),
+                credentials_valid,
+                last_successful_sync,
+                last_sync_error,
+            });
+        }
+    }
+
+    let ready = database && providers.iter().all(|provider| provider.credentials_valid);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    poem::web::Json(Readiness {
+        ready,
+        database,
+        providers,
+    })
+    .with_status(status)
+    .into_response()
+}
+
+/// When the provider last synced and the error of its latest sync, if that one failed
+async fn last_sync(state: &AppState, provider: &str) -> (Option<DateTime<Utc>>, Option<String>) {
+    let kind = JobKind::Domains.as_str();
+    let filter = JobRunFilter {
+        kind: Some(kind.to_string()),
+        provider: Some(provider.to_string()),
+        outcome: Some(OUTCOME_SUCCESS.to_string()),
+    };
+
+    let last_success = match JobRun::find_recent(state, &filter, 1).await {
+        Ok(runs) => runs.into_iter().next().and_then(|run| run.finished_at),
+        Err(e) => {
+            warn!("Readiness check: failed to load job runs: {}", e);
+            None
+        }
+    };
+
+    let last_error = match JobRun::find_last_finished(state, kind, provider).await {
+        Ok(run) => run
+            .filter(|run| run.outcome == OUTCOME_FAILURE)
+            .and_then(|run| run.error),
+        Err(e) => {
+            warn!("Readiness check: failed to load job runs: {}", e);
+            None
+        }
+    };
+
+    (last_success, last_error)
+}
+
+/// Validate the credentials of a provider with a single request, `None` if the provider isn't configured
+async fn check_credentials(state: &AppState, provider: &str) -> Option<bool> {
+    if let Some((checked_at, valid)) = CREDENTIALS.lock().unwrap().get(provider) {
+        if checked_at.elapsed() < CREDENTIALS_TTL {
+            return Some(*valid);
+        }
+    }
+
+    let valid = match provider {
+        "porkbun" => match state
+            .porkbun
+            .as_ref()?
+            .check_credentials(CREDENTIALS_TIMEOUT)
+            .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("Readiness check: porkbun ping failed: {}", e);
+                false
+            }
+        },
+        "cloudflare" => match state
+            .cloudflare
+            .as_ref()?
+            .check_credentials(CREDENTIALS_TIMEOUT)
+            .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("Readiness check: cloudflare get zones failed: {}", e);
+                false
+            }
+        },
+        _ => return None,
+    };
+
+    CREDENTIALS
+        .lock()
+        .unwrap()
+        .insert(provider.to_string(), (Instant::now(), valid));
+
+    Some(valid)
+}
@@ -0,0 +1,225 @@
+use std::{sync::LazyLock, time::Duration};
+
+use chrono::Utc;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use poem::{handler, http::StatusCode, web::Data, Request, Response};
+use prometheus::{
+    Encoder, GaugeVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use tracing::{error, info};
+
+use crate::{
+    models::{api_token::Scope, domain::Domain},
+    server::auth,
+    state::AppState,
+};
+
+/// Failed provider api requests, global so the shared http client can count them without the state
+static PROVIDER_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "dmn_provider_errors_total",
+            "Number of failed provider api requests",
+        ),
+        &["provider"],
+    )
+    .unwrap()
+});
+
+/// Count a failed provider api request, every retry counts separately
+pub fn record_provider_error(provider: &str) {
+    PROVIDER_ERRORS.with_label_values(&[provider]).inc();
+}
+
+/// Prometheus metrics exported on `/metrics`.
+/// Domain gauges are recomputed from the database on every scrape,
+/// job & provider metrics are updated as they happen.
+pub struct Metrics {
+    pub registry: Registry,
+    domains: IntGaugeVec,
+    domain_expiry_days: GaugeVec,
+    domains_auto_renew: IntGaugeVec,
+    domains_whois_privacy: IntGaugeVec,
+    sync_duration_seconds: GaugeVec,
+    sync_last_success_timestamp: IntGaugeVec,
+    sync_runs_total: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let domains = IntGaugeVec::new(
+            Opts::new("dmn_domains", "Number of domains per provider"),
+            &["provider"],
+        )
+        .unwrap();
+        let domain_expiry_days = GaugeVec::new(
+            Opts::new("dmn_domain_expiry_days", "Days until the domain expires"),
+            &["provider", "domain"],
+        )
+        .unwrap();
+        let domains_auto_renew = IntGaugeVec::new(
+            Opts::new("dmn_domains_auto_renew", "Number of domains with auto renew enabled"),
+            &["provider"],
+        )
+        .unwrap();
+        let domains_whois_privacy = IntGaugeVec::new(
+            Opts::new(
+                "dmn_domains_whois_privacy",
+                "Number of domains with whois privacy enabled",
+            ),
+            &["provider"],
+        )
+        .unwrap();
+        let sync_duration_seconds = GaugeVec::new(
+            Opts::new("dmn_sync_duration_seconds", "Duration of the last sync run"),
+            &["provider"],
+        )
+        .unwrap();
+        let sync_last_success_timestamp = IntGaugeVec::new(
+            Opts::new(
+                "dmn_sync_last_success_timestamp_seconds",
+                "Unix timestamp of the last successful sync",
+            ),
+            &["provider"],
+        )
+        .unwrap();
+        let sync_runs_total = IntCounterVec::new(
+            Opts::new("dmn_sync_runs_total", "Number of sync runs by outcome"),
+            &["provider", "outcome"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(domains.clone())).unwrap();
+        registry.register(Box::new(domain_expiry_days.clone())).unwrap();
+        registry.register(Box::new(domains_auto_renew.clone())).unwrap();
+        registry.register(Box::new(domains_whois_privacy.clone())).unwrap();
+        registry.register(Box::new(sync_duration_seconds.clone())).unwrap();
+        registry.register(Box::new(sync_last_success_timestamp.clone())).unwrap();
+        registry.register(Box::new(sync_runs_total.clone())).unwrap();
+        registry.register(Box::new(PROVIDER_ERRORS.clone())).unwrap();
+
+        Self {
+            registry,
+            domains,
+            domain_expiry_days,
+            domains_auto_renew,
+            domains_whois_privacy,
+            sync_duration_seconds,
+            sync_last_success_timestamp,
+            sync_runs_total,
+        }
+    }
+
+    /// Export the opentelemetry metrics (http requests, ...) into the same registry.
+    /// Must be called before any middleware grabs a meter from the global provider.
+    pub fn install_opentelemetry(&self) {
+        match opentelemetry_prometheus::exporter()
+            .with_registry(self.registry.clone())
+            .build()
+        {
+            Ok(exporter) => {
+                let provider = SdkMeterProvider::builder().with_reader(exporter).build();
+                opentelemetry::global::set_meter_provider(provider);
+                info!("Prometheus exporter installed");
+            }
+            Err(e) => {
+                error!("Failed to install prometheus exporter: {}", e);
+            }
+        }
+    }
+
+    /// Record the outcome of a sync
+    pub fn record_sync(&self, provider: &str, duration: Duration, success: bool) {
+        self.sync_duration_seconds
+            .with_label_values(&[provider])
+            .set(duration.as_secs_f64());
+
+        let outcome = if success {
+            self.sync_last_success_timestamp
+                .with_label_values(&[provider])
+                .set(Utc::now().timestamp());
+
+            "success"
+        } else {
+            "failure"
+        };
+
+        self.sync_runs_total
+            .with_label_values(&[provider, outcome])
+            .inc();
+    }
+
+    fn update_domains(&self, domains: &[Domain]) {
+        let now = Utc::now();
+
+        // reset so deleted domains disappear from the export
+        self.domains.reset();
+        self.domain_expiry_days.reset();
+        self.domains_auto_renew.reset();
+        self.domains_whois_privacy.reset();
+
+        for domain in domains {
+            let provider = domain.provider.as_str();
+
+            self.domains.with_label_values(&[provider]).inc();
+            // make sure every provider is exported, even with a count of 0
+            let auto_renew = self.domains_auto_renew.with_label_values(&[provider]);
+            let whois_privacy = self.domains_whois_privacy.with_label_values(&[provider]);
+
+            if domain.ext_auto_renew == Some(true) {
+                auto_renew.inc();
+            }
+            if domain.ext_whois_privacy == Some(true) {
+                whois_privacy.inc();
+            }
+
+            if let Some(expiry) = domain.ext_expiry_at {
+                let days = (expiry - now).num_seconds() as f64 / 86400.0;
+                self.domain_expiry_days
+                    .with_label_values(&[provider, &domain.name])
+                    .set(days);
+            }
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[handler]
+pub async fn route(state: Data<&AppState>, req: &Request) -> Response {
+    let principal = match auth::bearer_token(req) {
+        Some(token) => auth::authenticate(&state, token).await,
+        None => None,
+    };
+
+    match principal {
+        Some(principal) if principal.has_scope(Scope::ReadDomains) => {}
+        Some(_) => return Response::builder().status(StatusCode::FORBIDDEN).finish(),
+        None => return Response::builder().status(StatusCode::UNAUTHORIZED).finish(),
+    }
+
+    match Domain::get_all(&state).await {
+        Ok(domains) => state.metrics.update_domains(&domains),
+        Err(e) => error!("Failed to load domains for metrics: {}", e),
+    }
+
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+
+    if let Err(e) = encoder.encode(&state.metrics.registry.gather(), &mut buffer) {
+        error!("Failed to encode metrics: {}", e);
+        return Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .finish();
+    }
+
+    Response::builder()
+        .header("Content-Type", encoder.format_type())
+        .body(buffer)
+}
@@ -1,25 +1,41 @@
-use std::{num::NonZero, sync::Arc};
+use std::{num::NonZero, sync::Arc, time::Duration};
 
-use async_std::{prelude::FutureExt};
+use async_std::{prelude::FutureExt, task};
 use cal::CalApi;
 use domains::DomainApi;
 use governor::Quota;
+use notifications::NotificationApi;
 use poem::{
-    get, handler, listener::TcpListener, middleware::OpenTelemetryMetrics, web::Data,
+    get, handler,
+    listener::{BoxListener, Listener, RustlsCertificate, RustlsConfig, TcpListener},
+    middleware::OpenTelemetryMetrics,
+    web::Data,
     EndpointExt, Response, Route, Server,
 };
 use poem_openapi::{payload::Html, OpenApi, OpenApiService, Tags};
 
 use ratelimit::GovRateLimitMiddleware;
+use rss::RssApi;
+use sync::SyncApi;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::info;
+use tracing::{error, info, warn};
 
-use crate::{state::AppState, web};
+use crate::{modules::digest, state::AppState, web, Error};
 
+pub mod auth;
 pub mod cal;
 pub mod domains;
+pub mod health;
+pub mod metrics;
+pub mod notifications;
 pub mod ratelimit;
+pub mod rss;
 pub mod schedule;
+pub mod shutdown;
+pub mod sync;
+
+const DEFAULT_SHUTDOWN_TIMEOUT: &str = "30s";
 
 #[derive(Tags)]
 pub enum ApiTags {
@@ -31,10 +47,89 @@ pub enum ApiTags {
     Calendar,
     /// RSS Feed
     RSS,
+    /// Notifications & live event stream
+    Notifications,
+    /// Provider syncs & job history
+    Sync,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct HttpConfig {
+    pub host: Option<String>, // default 0.0.0.0
+    pub port: Option<u16>,    // default 3000
+    /// Listen on a unix socket instead of host/port
+    pub socket: Option<String>,
+    /// Public url of the server ("https://dmn.example.com"), used for links and the OpenAPI spec
+    pub base_url: Option<String>,
+    /// Serve https using this certificate & key (PEM)
+    pub tls_cert: Option<String>,
+    pub tls_key: Option<String>,
+    /// How long to wait for requests & running jobs on shutdown ("30s")
+    pub shutdown_timeout: Option<String>,
+}
+
+impl HttpConfig {
+    /// The configured public url without a trailing slash
+    pub fn base_url(&self) -> Option<String> {
+        self.base_url
+            .as_ref()
+            .map(|base_url| base_url.trim_end_matches('/').to_string())
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        let shutdown_timeout = self
+            .shutdown_timeout
+            .as_deref()
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+
+        humantime::parse_duration(shutdown_timeout).unwrap_or_else(|_| {
+            warn!(
+                "Invalid server shutdown_timeout `{}`, using {}",
+                shutdown_timeout, DEFAULT_SHUTDOWN_TIMEOUT
+            );
+            Duration::from_secs(30)
+        })
+    }
+
+    fn listener(&self) -> Result<BoxListener, Error> {
+        let listener = match &self.socket {
+            #[cfg(unix)]
+            Some(socket) => {
+                let socket = shellexpand::tilde(socket).to_string();
+                info!("Listening on unix socket {}", socket);
+                poem::listener::UnixListener::bind(socket).boxed()
+            }
+            #[cfg(not(unix))]
+            Some(_) => return Err(Error::msg("Unix sockets are not supported on this platform")),
+            None => {
+                let address = format!(
+                    "{}:{}",
+                    self.host.as_deref().unwrap_or("0.0.0.0"),
+                    self.port.unwrap_or(3000)
+                );
+                info!("Listening on {}", address);
+                TcpListener::bind(address).boxed()
+            }
+        };
+
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(cert), Some(key)) => {
+                let cert = std::fs::read(shellexpand::tilde(cert).as_ref())?;
+                let key = std::fs::read(shellexpand::tilde(key).as_ref())?;
+                let config =
+                    RustlsConfig::new().fallback(RustlsCertificate::new().cert(cert).key(key));
+
+                info!("TLS enabled");
+                Ok(listener.rustls(config).boxed())
+            }
+            (None, None) => Ok(listener),
+            _ => Err(Error::msg("Both tls_cert and tls_key are required to enable TLS")),
+        }
+    }
 }
 
 fn get_api(_state: AppState) -> impl OpenApi {
-    (DomainApi, CalApi)
+    (DomainApi, NotificationApi, SyncApi, CalApi, RssApi)
 }
 
 #[derive(Debug, Clone)]
@@ -167,7 +262,7 @@ pub async fn start_http(state: AppState) {
     let cargo_version = env!("CARGO_PKG_VERSION");
     let api_service = OpenApiService::new(get_api(state.clone()), "dmn", cargo_version)
         .description(description)
-        .server("http://localhost:3000/api");
+        .server(format!("{}/api", state.http.base_url().unwrap_or_default()));
 
     let spec_json = api_service.spec();
 
@@ -196,6 +291,9 @@ pub async fn start_http(state: AppState) {
         Quota::per_minute(NonZero::new(60).unwrap()),
     );
 
+    // the global meter provider must be set before the metrics middleware is created
+    state.metrics.install_opentelemetry();
+
     let api_service = api_service
         .with(limiter)
         // .with(TraceId::new(Arc::new(global::tracer("dmn"))))
@@ -206,16 +304,46 @@ pub async fn start_http(state: AppState) {
         .nest("/openapi.json", spec_route)
         .nest("/docs", get(get_openapi_docs))
         .nest("/api", api_service)
-        // .nest("/metrics", get(prom::route))
+        .nest("/metrics", get(metrics::route))
+        .nest("/healthz", get(health::healthz))
+        .nest("/readyz", get(health::readyz))
         .data(state.clone());
     // .with(Cors::new());
 
     let schedule_service = schedule::start_schedule(&state);
+    let release_service = digest::release_queued(&state);
 
-    let x = Server::new(TcpListener::bind("0.0.0.0:3000"))
-        .run(app);
+    let listener = match state.http.listener() {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to configure listener: {}", e);
+            return;
+        }
+    };
 
-    let _ = x.join(schedule_service).await;
+    let shutdown_timeout = state.http.shutdown_timeout();
+    let shutdown_requested = {
+        let state = state.clone();
+        async move { state.shutdown.requested().await }
+    };
+
+    // stops accepting connections once a shutdown is requested and drains the open ones
+    let x = Server::new(listener).run_with_graceful_shutdown(
+        app,
+        shutdown_requested,
+        Some(shutdown_timeout),
+    );
+
+    let signals = state.clone();
+    task::spawn(async move { shutdown::listen(&signals).await });
+
+    let _ = x.join(schedule_service).join(release_service).await;
+
+    // jobs started through the api run in the background
+    info!("Waiting for running jobs to finish");
+    if !state.shutdown.drain(shutdown_timeout).await {
+        warn!("Jobs still running after {:?}, exiting anyway", shutdown_timeout);
+    }
 }
 
 #[handler]
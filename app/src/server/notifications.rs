@@ -0,0 +1,220 @@
+use std::{pin::Pin, time::Duration};
+
+use async_broadcast::{InactiveReceiver, Receiver, Sender};
+use async_std::stream::{Stream, StreamExt};
+use chrono::{DateTime, Utc};
+use poem::{http::StatusCode, web::Data, Error};
+use poem_openapi::{
+    param::{Path, Query},
+    payload::{EventStream, Json},
+    ApiResponse, OpenApi,
+};
+use tracing::warn;
+
+use crate::{
+    models::{
+        api_token::Scope,
+        delivery::{Delivery, DeliveryFilter},
+        notification::{Notification, NotificationFilter},
+    },
+    server::{auth::ApiAuth, ApiTags},
+    state::AppState,
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 100;
+const MAX_PAGE_SIZE: u32 = 1000;
+const DEFAULT_DELIVERIES_LIMIT: u32 = 50;
+/// Notifications kept for slow stream subscribers before the oldest are dropped
+const STREAM_CAPACITY: usize = 256;
+const STREAM_KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+type NotificationStream = Pin<Box<dyn Stream<Item = Notification> + Send>>;
+
+/// Fans out newly created notifications to the live stream subscribers
+pub struct NotificationBus {
+    sender: Sender<Notification>,
+    // keeps the channel open while nobody is subscribed
+    receiver: InactiveReceiver<Notification>,
+}
+
+impl NotificationBus {
+    pub fn new() -> Self {
+        let (mut sender, receiver) = async_broadcast::broadcast(STREAM_CAPACITY);
+        sender.set_overflow(true);
+
+        Self {
+            sender,
+            receiver: receiver.deactivate(),
+        }
+    }
+
+    pub fn publish(&self, notification: &Notification) {
+        // without subscribers there is nobody to deliver to
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+
+        if let Err(e) = self.sender.try_broadcast(notification.clone()) {
+            warn!("Failed to publish notification {}: {}", notification.id, e);
+        }
+    }
+
+    pub fn subscribe(&self) -> Receiver<Notification> {
+        self.receiver.activate_cloned()
+    }
+}
+
+impl Default for NotificationBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(ApiResponse)]
+enum NotificationsResponse {
+    /// A page of notifications, newest first
+    #[oai(status = 200)]
+    Ok(
+        Json<Vec<Notification>>,
+        /// Pass as `cursor` to fetch the next page, absent on the last page
+        #[oai(header = "X-Next-Cursor")]
+        Option<String>,
+    ),
+}
+
+pub struct NotificationApi;
+
+#[OpenApi]
+impl NotificationApi {
+    /// List notifications
+    #[allow(clippy::too_many_arguments)]
+    #[oai(path = "/notifications", method = "get", tag = "ApiTags::Notifications")]
+    async fn get_notifications(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        /// Only notifications for this domain
+        domain: Query<Option<String>>,
+        /// Only notifications of this event ("add", "delete", "change", "expiry", "expiry_urgent")
+        event: Query<Option<String>>,
+        /// Only notifications created at or after this time
+        since: Query<Option<DateTime<Utc>>>,
+        /// Only notifications created before this time
+        until: Query<Option<DateTime<Utc>>>,
+        /// Only (un)acknowledged notifications
+        acknowledged: Query<Option<bool>>,
+        /// Cursor from the `X-Next-Cursor` header of the previous page
+        cursor: Query<Option<String>>,
+        /// Page size (default 100, max 1000)
+        limit: Query<Option<u32>>,
+    ) -> Result<NotificationsResponse, Error> {
+        auth.require(Scope::ReadDomains)?;
+
+        let filter = NotificationFilter {
+            domain: domain.0,
+            event: event.0,
+            since: since.0.map(|since| since.naive_utc()),
+            until: until.0.map(|until| until.naive_utc()),
+            acknowledged: acknowledged.0,
+        };
+
+        let before = match cursor.0 {
+            Some(cursor) => Some(
+                cursor
+                    .parse::<i64>()
+                    .map_err(|_| Error::from_string("Invalid cursor", StatusCode::BAD_REQUEST))?,
+            ),
+            None => None,
+        };
+
+        let limit = limit
+            .0
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        let (notifications, next) = Notification::find_filtered(&state, &filter, before, limit)
+            .await
+            .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+        Ok(NotificationsResponse::Ok(
+            Json(notifications),
+            next.map(|id| id.to_string()),
+        ))
+    }
+
+    /// Acknowledge a notification
+    #[oai(path = "/notifications/:id/ack", method = "post", tag = "ApiTags::Notifications")]
+    async fn ack_notification(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        id: Path<i64>,
+    ) -> Result<Json<Notification>, Error> {
+        auth.require(Scope::WriteDomains)?;
+
+        let notification = Notification::acknowledge(&state, id.0)
+            .await
+            .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?
+            .ok_or_else(|| Error::from_status(StatusCode::NOT_FOUND))?;
+
+        Ok(Json(notification))
+    }
+
+    /// List delivery attempts
+    ///
+    /// Every attempt at delivering a notification to a webhook channel, most recent first.
+    #[oai(path = "/deliveries", method = "get", tag = "ApiTags::Notifications")]
+    async fn get_deliveries(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        /// Only attempts for this channel
+        channel: Query<Option<String>>,
+        /// Only attempts for this notification
+        notification: Query<Option<i64>>,
+        /// Only successful (true) or failed (false) attempts
+        success: Query<Option<bool>>,
+        /// Number of attempts (default 50, max 1000)
+        limit: Query<Option<u32>>,
+    ) -> Result<Json<Vec<Delivery>>, Error> {
+        auth.require(Scope::ReadDomains)?;
+
+        let filter = DeliveryFilter {
+            channel: channel.0,
+            notification_id: notification.0,
+            success: success.0,
+        };
+        let limit = limit.0.unwrap_or(DEFAULT_DELIVERIES_LIMIT).clamp(1, MAX_PAGE_SIZE);
+
+        let deliveries = Delivery::find_recent(&state, &filter, limit)
+            .await
+            .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+        Ok(Json(deliveries))
+    }
+
+    /// Live stream of new notifications (server-sent events)
+    ///
+    /// Every add, delete and change detected during a sync is sent as a json event.
+    #[oai(path = "/notifications/stream", method = "get", tag = "ApiTags::Notifications")]
+    async fn stream_notifications(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        /// Only notifications for this domain
+        domain: Query<Option<String>>,
+        /// Only notifications of this event ("add", "delete", "change", "expiry", "expiry_urgent")
+        event: Query<Option<String>>,
+    ) -> Result<EventStream<NotificationStream>, Error> {
+        auth.require(Scope::ReadDomains)?;
+
+        let (domain, event) = (domain.0, event.0);
+        let stream: NotificationStream =
+            Box::pin(state.notification_bus.subscribe().filter(move |notification| {
+                domain.as_ref().is_none_or(|domain| &notification.domain == domain)
+                    && event.as_ref().is_none_or(|event| &notification.event == event)
+            }));
+
+        Ok(EventStream::new(stream).keep_alive(STREAM_KEEP_ALIVE))
+    }
+}
@@ -0,0 +1,563 @@
+use chrono::{DateTime, Duration, Utc};
+use poem::{http::StatusCode, web::Data, Error, Request};
+use poem_openapi::{
+    payload::{Json, PlainText},
+    ApiResponse, OpenApi, ResponseContent,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::{
+    models::domain::Domain,
+    server::{auth::FeedAuth, ApiTags},
+    state::AppState,
+};
+
+const DEFAULT_WARN_BEFORE: &str = "30 days";
+const DEFAULT_REGISTERED_WITHIN: &str = "30 days";
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct RssConfig {
+    pub enabled: Option<bool>, // default true
+    /// How long before expiry a domain shows up in the expiration feed ("30 days")
+    pub warn_before: Option<String>,
+    /// How long a newly registered domain stays in the registration feed ("30 days")
+    pub registered_within: Option<String>,
+    /// Secret required as `?token=...` to read the feeds
+    pub secret: Option<String>,
+}
+
+impl RssConfig {
+    pub fn warn_before(&self) -> Duration {
+        parse_window(
+            "warn_before",
+            self.warn_before.as_deref(),
+            DEFAULT_WARN_BEFORE,
+        )
+    }
+
+    pub fn registered_within(&self) -> Duration {
+        parse_window(
+            "registered_within",
+            self.registered_within.as_deref(),
+            DEFAULT_REGISTERED_WITHIN,
+        )
+    }
+}
+
+fn parse_window(name: &str, value: Option<&str>, default: &str) -> Duration {
+    let value = value.unwrap_or(default);
+
+    humantime::parse_duration(value)
+        .ok()
+        .and_then(|duration| Duration::from_std(duration).ok())
+        .unwrap_or_else(|| {
+            warn!("Invalid rss {} `{}`, using {}", name, value, default);
+            Duration::days(30)
+        })
+}
+
+#[derive(ResponseContent)]
+enum FeedContent {
+    #[oai(content_type = "application/rss+xml")]
+    Rss(PlainText<String>),
+    #[oai(content_type = "application/atom+xml")]
+    Atom(PlainText<String>),
+    #[oai(content_type = "application/feed+json")]
+    JsonFeed(Json<Value>),
+}
+
+#[derive(ApiResponse)]
+enum FeedResponse {
+    #[oai(status = 200)]
+    Ok(FeedContent),
+}
+
+#[derive(Clone, Copy)]
+enum FeedKind {
+    Expiration,
+    Registration,
+    All,
+}
+
+#[derive(Clone, Copy)]
+enum FeedFormat {
+    Rss,
+    Atom,
+    JsonFeed,
+}
+
+pub struct RssApi;
+
+#[OpenApi]
+impl RssApi {
+    /// Combined expiration & registration feed (RSS)
+    #[oai(path = "/rss.xml", method = "get", tag = "ApiTags::RSS")]
+    async fn get_rss(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        auth: FeedAuth,
+    ) -> Result<FeedResponse, Error> {
+        serve_feed(&state, req, auth, FeedKind::All, FeedFormat::Rss).await
+    }
+
+    /// Domains expiring soon (RSS)
+    #[oai(path = "/expiration.xml", method = "get", tag = "ApiTags::RSS")]
+    async fn get_expiration_rss(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        auth: FeedAuth,
+    ) -> Result<FeedResponse, Error> {
+        serve_feed(&state, req, auth, FeedKind::Expiration, FeedFormat::Rss).await
+    }
+
+    /// Domains expiring soon (Atom)
+    #[oai(path = "/expiration.atom", method = "get", tag = "ApiTags::RSS")]
+    async fn get_expiration_atom(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        auth: FeedAuth,
+    ) -> Result<FeedResponse, Error> {
+        serve_feed(&state, req, auth, FeedKind::Expiration, FeedFormat::Atom).await
+    }
+
+    /// Domains expiring soon (JSON Feed)
+    #[oai(path = "/expiration.json", method = "get", tag = "ApiTags::RSS")]
+    async fn get_expiration_json(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        auth: FeedAuth,
+    ) -> Result<FeedResponse, Error> {
+        serve_feed(&state, req, auth, FeedKind::Expiration, FeedFormat::JsonFeed).await
+    }
+
+    /// Newly registered domains (RSS)
+    #[oai(path = "/registration.xml", method = "get", tag = "ApiTags::RSS")]
+    async fn get_registration_rss(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        auth: FeedAuth,
+    ) -> Result<FeedResponse, Error> {
+        serve_feed(&state, req, auth, FeedKind::Registration, FeedFormat::Rss).await
+    }
+
+    /// Newly registered domains (Atom)
+    #[oai(path = "/registration.atom", method = "get", tag = "ApiTags::RSS")]
+    async fn get_registration_atom(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        auth: FeedAuth,
+    ) -> Result<FeedResponse, Error> {
+        serve_feed(&state, req, auth, FeedKind::Registration, FeedFormat::Atom).await
+    }
+
+    /// Newly registered domains (JSON Feed)
+    #[oai(path = "/registration.json", method = "get", tag = "ApiTags::RSS")]
+    async fn get_registration_json(
+        &self,
+        state: Data<&AppState>,
+        req: &Request,
+        auth: FeedAuth,
+    ) -> Result<FeedResponse, Error> {
+        serve_feed(&state, req, auth, FeedKind::Registration, FeedFormat::JsonFeed).await
+    }
+}
+
+async fn serve_feed(
+    state: &AppState,
+    req: &Request,
+    auth: FeedAuth,
+    kind: FeedKind,
+    format: FeedFormat,
+) -> Result<FeedResponse, Error> {
+    if !state.rss.enabled.unwrap_or(true) {
+        return Err(Error::from_status(StatusCode::NOT_FOUND));
+    }
+
+    auth.verify("rss", state.rss.secret.as_deref())?;
+
+    let domains = Domain::get_all(state)
+        .await
+        .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    let base_url = base_url(state, req);
+    let feed = build_feed(
+        &domains,
+        kind,
+        &state.rss,
+        &base_url,
+        req.uri().path(),
+        Utc::now(),
+    );
+
+    let content = match format {
+        FeedFormat::Rss => FeedContent::Rss(PlainText(feed.to_rss())),
+        FeedFormat::Atom => FeedContent::Atom(PlainText(feed.to_atom())),
+        FeedFormat::JsonFeed => FeedContent::JsonFeed(Json(feed.to_json_feed())),
+    };
+
+    Ok(FeedResponse::Ok(content))
+}
+
+/// The public url of the server, derived from the request when `base_url` is not configured
+fn base_url(state: &AppState, req: &Request) -> String {
+    if let Some(base_url) = state.http.base_url() {
+        return base_url;
+    }
+
+    let host = req
+        .headers()
+        .get("host")
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost:3000");
+
+    format!("http://{}", host)
+}
+
+struct FeedItem {
+    /// Stable identifier so readers don't show the same event twice
+    id: String,
+    title: String,
+    summary: String,
+    published: DateTime<Utc>,
+}
+
+struct Feed {
+    title: String,
+    description: String,
+    /// Link to the dashboard
+    link: String,
+    /// Link to the feed itself
+    self_link: String,
+    items: Vec<FeedItem>,
+}
+
+fn build_feed(
+    domains: &[Domain],
+    kind: FeedKind,
+    config: &RssConfig,
+    base_url: &str,
+    path: &str,
+    now: DateTime<Utc>,
+) -> Feed {
+    let warn_before = config.warn_before();
+    let registered_within = config.registered_within();
+    let mut items = Vec::new();
+
+    if matches!(kind, FeedKind::Expiration | FeedKind::All) {
+        for domain in domains {
+            let Some(expiry) = domain.ext_expiry_at else {
+                continue;
+            };
+
+            let published = expiry - warn_before;
+            if published > now {
+                continue;
+            }
+
+            // keep the title free of relative dates, so readers don't treat it as an update
+            let title = format!("{} expires on {}", domain.name, expiry.format("%Y-%m-%d"));
+
+            let renewal = match domain.ext_auto_renew {
+                Some(true) => "Auto renew is enabled.",
+                Some(false) => "Auto renew is disabled.",
+                None => "Auto renew status is unknown.",
+            };
+
+            items.push(FeedItem {
+                // the expiry date is part of the id, so a renewed domain shows up again next cycle
+                id: format!(
+                    "urn:dmn:expiration:{}:{}:{}",
+                    domain.provider,
+                    domain.name,
+                    expiry.format("%Y-%m-%d")
+                ),
+                title,
+                summary: format!(
+                    "{} ({}) expires on {}. {}",
+                    domain.name,
+                    domain.provider,
+                    expiry.format("%Y-%m-%d"),
+                    renewal
+                ),
+                published,
+            });
+        }
+    }
+
+    if matches!(kind, FeedKind::Registration | FeedKind::All) {
+        for domain in domains {
+            let Some(registered) = domain.ext_registered_at else {
+                continue;
+            };
+
+            // only newly registered domains, not the whole portfolio
+            if registered < now - registered_within {
+                continue;
+            }
+
+            items.push(FeedItem {
+                id: format!(
+                    "urn:dmn:registration:{}:{}:{}",
+                    domain.provider,
+                    domain.name,
+                    registered.format("%Y-%m-%d")
+                ),
+                title: format!("{} was registered", domain.name),
+                summary: format!(
+                    "{} was registered at {} on {}.",
+                    domain.name,
+                    domain.provider,
+                    registered.format("%Y-%m-%d")
+                ),
+                published: registered,
+            });
+        }
+    }
+
+    items.sort_by_key(|item| std::cmp::Reverse(item.published));
+
+    let (title, description) = match kind {
+        FeedKind::Expiration => ("Expiring domains", "Domains that are about to expire"),
+        FeedKind::Registration => ("Registered domains", "Newly registered domains"),
+        FeedKind::All => ("Domains", "Expiring and newly registered domains"),
+    };
+
+    Feed {
+        title: format!("{} | dmn", title),
+        description: description.to_string(),
+        link: format!("{}/", base_url),
+        self_link: format!("{}{}", base_url, path),
+        items,
+    }
+}
+
+impl Feed {
+    fn updated(&self) -> DateTime<Utc> {
+        self.items
+            .iter()
+            .map(|item| item.published)
+            .max()
+            .unwrap_or_else(Utc::now)
+    }
+
+    fn to_rss(&self) -> String {
+        let mut xml = String::new();
+
+        xml += r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+        xml += r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>"#;
+        xml += &format!("<title>{}</title>", escape_xml(&self.title));
+        xml += &format!("<link>{}</link>", escape_xml(&self.link));
+        xml += &format!("<description>{}</description>", escape_xml(&self.description));
+        xml += &format!(
+            r#"<atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+            escape_xml(&self.self_link)
+        );
+        xml += &format!("<lastBuildDate>{}</lastBuildDate>", self.updated().to_rfc2822());
+
+        for item in &self.items {
+            xml += "<item>";
+            xml += &format!("<title>{}</title>", escape_xml(&item.title));
+            xml += &format!("<link>{}</link>", escape_xml(&self.link));
+            xml += &format!("<description>{}</description>", escape_xml(&item.summary));
+            xml += &format!(
+                r#"<guid isPermaLink="false">{}</guid>"#,
+                escape_xml(&item.id)
+            );
+            xml += &format!("<pubDate>{}</pubDate>", item.published.to_rfc2822());
+            xml += "</item>";
+        }
+
+        xml += "</channel></rss>";
+        xml
+    }
+
+    fn to_atom(&self) -> String {
+        let mut xml = String::new();
+
+        xml += r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+        xml += r#"<feed xmlns="http://www.w3.org/2005/Atom">"#;
+        xml += &format!("<id>{}</id>", escape_xml(&self.self_link));
+        xml += &format!("<title>{}</title>", escape_xml(&self.title));
+        xml += &format!("<subtitle>{}</subtitle>", escape_xml(&self.description));
+        xml += &format!(r#"<link href="{}"/>"#, escape_xml(&self.link));
+        xml += &format!(r#"<link href="{}" rel="self"/>"#, escape_xml(&self.self_link));
+        xml += &format!("<updated>{}</updated>", self.updated().to_rfc3339());
+        xml += "<author><name>dmn</name></author>";
+
+        for item in &self.items {
+            xml += "<entry>";
+            xml += &format!("<id>{}</id>", escape_xml(&item.id));
+            xml += &format!("<title>{}</title>", escape_xml(&item.title));
+            xml += &format!(r#"<link href="{}"/>"#, escape_xml(&self.link));
+            xml += &format!("<summary>{}</summary>", escape_xml(&item.summary));
+            xml += &format!("<published>{}</published>", item.published.to_rfc3339());
+            xml += &format!("<updated>{}</updated>", item.published.to_rfc3339());
+            xml += "</entry>";
+        }
+
+        xml += "</feed>";
+        xml
+    }
+
+    /// See https://www.jsonfeed.org/version/1.1/
+    fn to_json_feed(&self) -> Value {
+        json!({
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": self.title,
+            "description": self.description,
+            "home_page_url": self.link,
+            "feed_url": self.self_link,
+            "items": self.items.iter().map(|item| json!({
+                "id": item.id,
+                "url": self.link,
+                "title": item.title,
+                "content_text": item.summary,
+                "date_published": item.published.to_rfc3339(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(at: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(at).unwrap().to_utc()
+    }
+
+    fn domain(name: &str, expiry: &str, registered: &str) -> Domain {
+        Domain {
+            name: name.to_string(),
+            provider: "porkbun".to_string(),
+            external_id: None,
+            ext_expiry_at: Some(utc(expiry)),
+            ext_registered_at: Some(utc(registered)),
+            ext_auto_renew: Some(false),
+            ext_whois_privacy: None,
+            metadata: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_build_feed() {
+        let now = utc("2025-04-26T08:00:00Z");
+        let config = RssConfig {
+            warn_before: Some("30 days".to_string()),
+            registered_within: Some("7 days".to_string()),
+            ..Default::default()
+        };
+        let domains = [
+            domain("soon.com", "2025-05-06T00:00:00Z", "2020-05-06T00:00:00Z"),
+            domain("later.com", "2026-01-01T00:00:00Z", "2025-04-20T00:00:00Z"),
+        ];
+
+        let feed = build_feed(
+            &domains,
+            FeedKind::Expiration,
+            &config,
+            "https://dmn.example",
+            "/api/expiration.xml",
+            now,
+        );
+        assert_eq!(feed.items.len(), 1);
+        assert_eq!(
+            feed.items[0].id,
+            "urn:dmn:expiration:porkbun:soon.com:2025-05-06"
+        );
+        // shows up warn_before ahead of the expiry
+        assert_eq!(feed.items[0].published, utc("2025-04-06T00:00:00Z"));
+
+        // only domains registered within the window, not the whole portfolio
+        let feed = build_feed(
+            &domains,
+            FeedKind::Registration,
+            &config,
+            "https://dmn.example",
+            "/api/registration.xml",
+            now,
+        );
+        assert_eq!(feed.items.len(), 1);
+        assert_eq!(
+            feed.items[0].id,
+            "urn:dmn:registration:porkbun:later.com:2025-04-20"
+        );
+
+        // ids don't change between builds, only when the domain is renewed
+        let later = build_feed(
+            &domains,
+            FeedKind::All,
+            &config,
+            "https://dmn.example",
+            "/api/rss.xml",
+            now + Duration::hours(1),
+        );
+        let ids = |feed: &Feed| {
+            feed.items
+                .iter()
+                .map(|item| item.id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(&later),
+            ids(&build_feed(
+                &domains,
+                FeedKind::All,
+                &config,
+                "https://dmn.example",
+                "/api/rss.xml",
+                now
+            ))
+        );
+
+        let mut renewed = domains.clone();
+        renewed[0].ext_expiry_at = Some(utc("2026-05-06T00:00:00Z"));
+        let feed = build_feed(
+            &renewed,
+            FeedKind::Expiration,
+            &config,
+            "https://dmn.example",
+            "/api/expiration.xml",
+            utc("2026-04-26T08:00:00Z"),
+        );
+        assert_eq!(
+            feed.items[0].id,
+            "urn:dmn:expiration:porkbun:soon.com:2026-05-06"
+        );
+
+        let atom = later.to_atom();
+        assert!(atom.contains("<id>urn:dmn:expiration:porkbun:soon.com:2025-05-06</id>"));
+        assert!(atom.contains(r#"<link href="https://dmn.example/api/rss.xml" rel="self"/>"#));
+
+        let json = later.to_json_feed();
+        assert_eq!(json["feed_url"], "https://dmn.example/api/rss.xml");
+        // newest first
+        assert_eq!(
+            json["items"][0]["id"],
+            "urn:dmn:registration:porkbun:later.com:2025-04-20"
+        );
+        assert_eq!(
+            json["items"][1]["date_published"],
+            "2025-04-06T00:00:00+00:00"
+        );
+    }
+}
@@ -1,48 +1,332 @@
-use std::time::Duration;
+use std::{fmt, future::Future, str::FromStr, time::Duration};
 
-use async_std::stream::{self, StreamExt};
-use tracing::{info, warn};
+use async_std::{prelude::FutureExt, task};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
 
-use crate::{modules::{domains::diff_provider, DomainService}, state::AppState, Error};
+use crate::{
+    models::job_run::{JobRun, TRIGGER_SCHEDULE},
+    modules::{DNSService, DomainService},
+    server::sync::{is_configured, sync_provider, PROVIDERS},
+    state::AppState,
+    Error,
+};
 
-pub async fn start_schedule(state: &AppState) {
-    match do_loop(state).await {
-        Ok(_) => (),
-        Err(e) => {
-            tracing::error!("Error in schedule: {}", e);
+/// When each background job runs, either a duration ("1h", "1d") or a cron expression
+/// ("0 */6 * * *", "0 0 9 * * Mon"). Use "off" to disable a job.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ScheduleConfig {
+    /// Sync domains from the providers (default "1h")
+    pub domains: Option<String>,
+    /// Sync dns records (default "off", dns records aren't stored yet)
+    pub dns: Option<String>,
+    /// Download tld prices (default `porkbun.pricing.interval` or "1d")
+    pub pricing: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    Domains,
+    Dns,
+    Pricing,
+}
+
+impl JobKind {
+    pub const ALL: &'static [JobKind] = &[JobKind::Domains, JobKind::Dns, JobKind::Pricing];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::Domains => "domains",
+            JobKind::Dns => "dns",
+            JobKind::Pricing => "pricing",
         }
     }
 
-    // 1 hr by default
-    let mut ticks = stream::interval(Duration::from_secs(60 * 60));
+    fn default_schedule(&self) -> &'static str {
+        match self {
+            JobKind::Domains => "1h",
+            // only lists the zones until dns records are stored
+            JobKind::Dns => "off",
+            JobKind::Pricing => "1d",
+        }
+    }
+}
 
-    while (ticks.next().await).is_some() {
-        match do_loop(state).await {
-            Ok(_) => (),
-            Err(e) => {
-                tracing::error!("Error in schedule: {}", e);
+impl fmt::Display for JobKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum JobSchedule {
+    Interval(Duration),
+    Cron(Box<cron::Schedule>),
+    Disabled,
+}
+
+impl JobSchedule {
+    /// The first run after startup. Intervals continue from the last run (right away when it's
+    /// overdue or there is none), so restarts don't re-run every job. Cron jobs wait for their slot.
+    fn first_run(
+        &self,
+        now: DateTime<Utc>,
+        last_run: Option<DateTime<Utc>>,
+    ) -> Option<DateTime<Utc>> {
+        match (self, last_run) {
+            (JobSchedule::Interval(_), Some(last_run)) => {
+                self.next_run(last_run).map(|next_run| next_run.max(now))
             }
+            (JobSchedule::Interval(_), None) => Some(now),
+            _ => self.next_run(now),
+        }
+    }
+
+    fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            JobSchedule::Interval(interval) => {
+                Some(after + chrono::Duration::from_std(*interval).ok()?)
+            }
+            JobSchedule::Cron(schedule) => schedule.after(&after).next(),
+            JobSchedule::Disabled => None,
         }
     }
 }
 
-async fn do_loop(state: &AppState) -> Result<(), Error> {
-    if let Some(porkbun) = &state.porkbun {
-        let notifications = diff_provider(&state, "porkbun", porkbun).await?;
+impl FromStr for JobSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
 
-        if let Some(ntfy) = &state.ntfy {
-            if !notifications.is_empty() {
-                info!("Sending notifications to Ntfy");
-                ntfy.send_notifications(notifications).await?;
-            } else {
-                info!("No notifications to send");
+        if matches!(s, "off" | "never" | "disabled") {
+            return Ok(JobSchedule::Disabled);
+        }
+
+        if let Ok(interval) = humantime::parse_duration(s) {
+            if interval.is_zero() {
+                return Err("Interval must be greater than zero".to_string());
             }
+
+            return Ok(JobSchedule::Interval(interval));
+        }
+
+        // standard 5 field cron expressions have no seconds field
+        let expression = if s.split_whitespace().count() == 5 {
+            format!("0 {}", s)
         } else {
-            warn!("Ntfy service not initialized");
+            s.to_string()
+        };
+
+        cron::Schedule::from_str(&expression)
+            .map(|schedule| JobSchedule::Cron(Box::new(schedule)))
+            .map_err(|e| format!("`{}` is neither a duration nor a cron expression: {}", s, e))
+    }
+}
+
+impl ScheduleConfig {
+    pub fn job(&self, state: &AppState, kind: JobKind) -> JobSchedule {
+        let configured = match kind {
+            JobKind::Domains => self.domains.clone(),
+            JobKind::Dns => self.dns.clone(),
+            JobKind::Pricing => self.pricing.clone().or_else(|| {
+                state
+                    .porkbun
+                    .as_ref()
+                    .and_then(|porkbun| porkbun.config.pricing.as_ref())
+                    .and_then(|pricing| pricing.interval.clone())
+            }),
+        };
+
+        configured
+            .as_deref()
+            .unwrap_or(kind.default_schedule())
+            .parse()
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Invalid schedule for {} ({}), using {}",
+                    kind,
+                    e,
+                    kind.default_schedule()
+                );
+                kind.default_schedule().parse().unwrap()
+            })
+    }
+}
+
+struct Job {
+    kind: JobKind,
+    schedule: JobSchedule,
+    next_run: Option<DateTime<Utc>>,
+}
+
+pub async fn start_schedule(state: &AppState) {
+    match JobRun::fail_interrupted(state).await {
+        Ok(0) => {}
+        Ok(count) => warn!("Marked {} interrupted job runs as failed", count),
+        Err(e) => error!("Failed to clean up interrupted job runs: {}", e),
+    }
+
+    let now = Utc::now();
+    let mut jobs = Vec::new();
+    for kind in JobKind::ALL {
+        let schedule = state.schedule.job(state, *kind);
+        let last_run = match JobRun::find_last_started(state, kind.as_str()).await {
+            Ok(run) => run.map(|run| run.started_at),
+            Err(e) => {
+                error!("Failed to load the last {} run: {}", kind, e);
+                None
+            }
+        };
+        let next_run = schedule.first_run(now, last_run);
+
+        match next_run {
+            Some(next_run) => info!("Scheduled {} job, first run at {}", kind, next_run),
+            None => info!("The {} job is disabled", kind),
         }
 
-        porkbun.ingest_domain_tld_prices_if_enabled(state).await?;
+        jobs.push(Job {
+            kind: *kind,
+            schedule,
+            next_run,
+        });
+    }
+
+    loop {
+        let Some(next_run) = jobs.iter().filter_map(|job| job.next_run).min() else {
+            info!("No scheduled jobs");
+            return;
+        };
+
+        // wake up early to stop on shutdown
+        task::sleep((next_run - Utc::now()).to_std().unwrap_or_default())
+            .race(state.shutdown.requested())
+            .await;
+
+        for job in jobs.iter_mut() {
+            if state.shutdown.is_requested() {
+                info!("Schedule stopped");
+                return;
+            }
+
+            if job.next_run.is_none_or(|next_run| next_run > Utc::now()) {
+                continue;
+            }
+
+            if let Err(e) = run_job(state, job.kind).await {
+                error!("Error in {} job: {}", job.kind, e);
+            }
+
+            job.next_run = job.schedule.next_run(Utc::now());
+            if let Some(next_run) = job.next_run {
+                info!("Next {} run at {}", job.kind, next_run);
+            }
+        }
+    }
+}
+
+async fn run_job(state: &AppState, kind: JobKind) -> Result<(), Error> {
+    match kind {
+        JobKind::Domains => {
+            // providers are synced independently, one failing provider doesn't stop the others
+            let mut failed = Vec::new();
+
+            for provider in PROVIDERS.iter().filter(|provider| is_configured(state, provider)) {
+                if let Err(e) = sync_provider(state, provider, TRIGGER_SCHEDULE).await {
+                    failed.push(format!("{}: {}", provider, e));
+                }
+            }
+
+            if !failed.is_empty() {
+                return Err(Error::msg(failed.join(", ")));
+            }
+        }
+        JobKind::Dns => {
+            if let Some(cloudflare) = &state.cloudflare {
+                record(
+                    state,
+                    kind,
+                    "cloudflare",
+                    TRIGGER_SCHEDULE,
+                    cloudflare.ingest_dns_domains(state),
+                )
+                .await?;
+            }
+        }
+        JobKind::Pricing => {
+            if let Some(porkbun) = &state.porkbun {
+                record(
+                    state,
+                    kind,
+                    "porkbun",
+                    TRIGGER_SCHEDULE,
+                    porkbun.ingest_domain_tld_prices_if_enabled(state),
+                )
+                .await?;
+            }
+        }
     }
 
     Ok(())
 }
+
+/// Run a job that doesn't produce notifications, keeping track of it in the job history
+pub async fn record(
+    state: &AppState,
+    kind: JobKind,
+    provider: &str,
+    trigger: &str,
+    job: impl Future<Output = Result<(), Error>>,
+) -> Result<(), Error> {
+    let Some(_job) = state.shutdown.job().await else {
+        return Err(Error::msg("Shutting down"));
+    };
+
+    let run = JobRun::start(state, kind.as_str(), Some(provider), trigger).await?;
+
+    match job.await {
+        Ok(()) => {
+            run.succeed(state, &[]).await?;
+            Ok(())
+        }
+        Err(e) => {
+            run.fail(state, &e.to_string()).await?;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(at: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(at).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_first_run() {
+        let now = utc("2025-04-26T08:00:00Z");
+        let daily = "1d".parse::<JobSchedule>().unwrap();
+
+        // a restart doesn't re-run a job that ran recently
+        assert_eq!(
+            daily.first_run(now, Some(utc("2025-04-26T06:00:00Z"))),
+            Some(utc("2025-04-27T06:00:00Z"))
+        );
+        // overdue or never run, right away
+        assert_eq!(
+            daily.first_run(now, Some(utc("2025-04-20T06:00:00Z"))),
+            Some(now)
+        );
+        assert_eq!(daily.first_run(now, None), Some(now));
+
+        let cron = "0 */6 * * *".parse::<JobSchedule>().unwrap();
+        assert_eq!(cron.first_run(now, None), Some(utc("2025-04-26T12:00:00Z")));
+        assert_eq!(
+            "off".parse::<JobSchedule>().unwrap().first_run(now, None),
+            None
+        );
+    }
+}
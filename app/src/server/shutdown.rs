@@ -0,0 +1,109 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use async_broadcast::{InactiveReceiver, Sender};
+use async_signal::{Signal, Signals};
+use async_std::{
+    future::timeout,
+    stream::StreamExt,
+    sync::{RwLock, RwLockReadGuard},
+};
+use tracing::{error, info, warn};
+
+use crate::state::AppState;
+
+/// Tracks whether the server is shutting down and which jobs are still running
+pub struct Shutdown {
+    requested: AtomicBool,
+    sender: Sender<()>,
+    // keeps the channel open while nobody is waiting
+    receiver: InactiveReceiver<()>,
+    /// Every running job holds a read guard, draining takes the write lock
+    jobs: RwLock<()>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (mut sender, receiver) = async_broadcast::broadcast(1);
+        sender.set_overflow(true);
+
+        Self {
+            requested: AtomicBool::new(false),
+            sender,
+            receiver: receiver.deactivate(),
+            jobs: RwLock::new(()),
+        }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    pub fn request(&self) {
+        if !self.requested.swap(true, Ordering::SeqCst) {
+            let _ = self.sender.try_broadcast(());
+        }
+    }
+
+    /// Resolves once a shutdown was requested
+    pub async fn requested(&self) {
+        let mut receiver = self.receiver.activate_cloned();
+
+        if !self.is_requested() {
+            let _ = receiver.recv().await;
+        }
+    }
+
+    /// Register a running job, `None` when shutting down and no new jobs should start.
+    /// The job counts as running until the guard is dropped.
+    pub async fn job(&self) -> Option<RwLockReadGuard<'_, ()>> {
+        let guard = self.jobs.read().await;
+
+        if self.is_requested() {
+            return None;
+        }
+
+        Some(guard)
+    }
+
+    /// Wait for the running jobs to finish, `false` if they didn't within `limit`
+    pub async fn drain(&self, limit: Duration) -> bool {
+        timeout(limit, self.jobs.write()).await.is_ok()
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Request a shutdown on SIGTERM or SIGINT (ctrl-c)
+pub async fn listen(state: &AppState) {
+    let mut signals = match Signals::new([Signal::Term, Signal::Int]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            error!("Failed to listen for shutdown signals: {}", e);
+            return;
+        }
+    };
+
+    // a second signal while draining skips the graceful part
+    let mut received = false;
+    while let Some(signal) = signals.next().await {
+        let Ok(signal) = signal else {
+            continue;
+        };
+
+        if received {
+            warn!("Received {:?} again, exiting immediately", signal);
+            std::process::exit(1);
+        }
+
+        info!("Received {:?}, shutting down", signal);
+        received = true;
+        state.shutdown.request();
+    }
+}
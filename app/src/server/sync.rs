@@ -0,0 +1,372 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use async_std::{prelude::FutureExt, task};
+use chrono::{DateTime, Utc};
+use poem::{http::StatusCode, web::Data, Error};
+use poem_openapi::{
+    param::{Path, Query},
+    payload::Json,
+    ApiResponse, Enum, Object, OpenApi,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    models::{
+        api_token::Scope,
+        job_run::{JobRun, JobRunFilter, TRIGGER_API},
+        notification::Notification,
+        sync_lock::{SyncAlreadyRunning, SyncLock},
+    },
+    modules::{domains::diff_provider, DomainService},
+    server::{auth::ApiAuth, schedule::JobKind, ApiTags},
+    state::AppState,
+};
+
+/// Every provider that can be synced
+pub const PROVIDERS: &[&str] = &["porkbun", "cloudflare"];
+
+/// Finished jobs kept around for `GET /sync/:id`
+const MAX_FINISHED_JOBS: usize = 100;
+
+const DEFAULT_JOBS_LIMIT: u32 = 50;
+const MAX_JOBS_LIMIT: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+#[oai(rename_all = "snake_case")]
+pub enum SyncStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Object)]
+pub struct SyncJob {
+    pub id: u64,
+    pub provider: String,
+    pub status: SyncStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    pub added: u32,
+    pub deleted: u32,
+    pub changed: u32,
+}
+
+/// Background syncs started through the api
+pub struct SyncJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<u64, SyncJob>>,
+}
+
+impl SyncJobs {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            jobs: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Start syncing `provider` in the background.
+    /// If a sync for the provider is already running that job is returned instead.
+    pub fn start(&self, state: &AppState, provider: &str) -> SyncJob {
+        let mut jobs = self.jobs.lock().unwrap();
+
+        if let Some(job) = jobs
+            .values()
+            .find(|job| job.provider == provider && job.status == SyncStatus::Running)
+        {
+            info!("Sync for {} already running as job {}", provider, job.id);
+            return job.clone();
+        }
+
+        let job = SyncJob {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            provider: provider.to_string(),
+            status: SyncStatus::Running,
+            started_at: Utc::now(),
+            finished_at: None,
+            error: None,
+            added: 0,
+            deleted: 0,
+            changed: 0,
+        };
+        jobs.insert(job.id, job.clone());
+        Self::prune(&mut jobs);
+        drop(jobs);
+
+        let state = state.clone();
+        let (id, provider) = (job.id, job.provider.clone());
+        task::spawn(async move {
+            let result = sync_provider(&state, &provider, TRIGGER_API).await;
+            state.sync_jobs.finish(id, result);
+        });
+
+        job
+    }
+
+    pub fn get(&self, id: u64) -> Option<SyncJob> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+
+    fn finish(&self, id: u64, result: Result<Vec<Notification>, crate::Error>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.get_mut(&id) else {
+            return;
+        };
+
+        job.finished_at = Some(Utc::now());
+        match result {
+            Ok(notifications) => {
+                let count = |event: &str| {
+                    notifications.iter().filter(|n| n.event == event).count() as u32
+                };
+
+                job.status = SyncStatus::Succeeded;
+                job.added = count("add");
+                job.deleted = count("delete");
+                job.changed = count("change");
+            }
+            Err(e) => {
+                job.status = SyncStatus::Failed;
+                job.error = Some(e.to_string());
+            }
+        }
+    }
+
+    fn prune(jobs: &mut BTreeMap<u64, SyncJob>) {
+        let finished = jobs
+            .values()
+            .filter(|job| job.status != SyncStatus::Running)
+            .map(|job| job.id)
+            .collect::<Vec<_>>();
+
+        // ids are sequential, so the first ones are the oldest
+        for id in finished.iter().take(finished.len().saturating_sub(MAX_FINISHED_JOBS)) {
+            jobs.remove(id);
+        }
+    }
+}
+
+impl Default for SyncJobs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether credentials for `provider` are configured
+pub fn is_configured(state: &AppState, provider: &str) -> bool {
+    match provider {
+        "porkbun" => state.porkbun.is_some(),
+        "cloudflare" => state.cloudflare.is_some(),
+        _ => false,
+    }
+}
+
+/// Sync a configured provider, see [`sync_domains`]
+pub async fn sync_provider(
+    state: &AppState,
+    provider: &str,
+    trigger: &str,
+) -> Result<Vec<Notification>, crate::Error> {
+    match provider {
+        "porkbun" => match &state.porkbun {
+            Some(porkbun) => sync_domains(state, provider, porkbun, trigger).await,
+            None => Err(crate::Error::msg("Porkbun is not configured")),
+        },
+        "cloudflare" => match &state.cloudflare {
+            Some(cloudflare) => sync_domains(state, provider, cloudflare, trigger).await,
+            None => Err(crate::Error::msg("Cloudflare is not configured")),
+        },
+        _ => Err(crate::Error::msg(format!("Unknown provider {}", provider))),
+    }
+}
+
+/// Diff the domains of a provider, record the run & metrics and send out the notifications.
+/// `trigger` is what started the sync ("schedule", "api" or "cli"), kept in the job history.
+pub async fn sync_domains(
+    state: &AppState,
+    provider: &str,
+    service: &impl DomainService,
+    trigger: &str,
+) -> Result<Vec<Notification>, crate::Error> {
+    // a shutdown waits for running syncs, new ones aren't started anymore
+    let Some(_job) = state.shutdown.job().await else {
+        return Err(crate::Error::msg("Shutting down"));
+    };
+
+    let run = JobRun::start(state, JobKind::Domains.as_str(), Some(provider), trigger).await?;
+
+    // only one process syncs a provider at a time, e.g. `dmn porkbun index` next to the server
+    let Some(lock) = SyncLock::acquire(state, provider, &run).await? else {
+        let running = SyncLock::find_active(state, provider)
+            .await?
+            .map(|lock| SyncAlreadyRunning(lock).to_string())
+            .unwrap_or_else(|| format!("Sync for {} already running", provider));
+
+        warn!("{}", running);
+        if let Err(e) = run.skip(state, &running).await {
+            error!("Failed to record job run {}: {}", run.id, e);
+        }
+
+        return Err(crate::Error::msg(running));
+    };
+
+    let started = Instant::now();
+    let result = diff_provider(state, provider, service)
+        .race(lock.keep_alive(state))
+        .await;
+
+    if let Err(e) = lock.release(state).await {
+        error!("Failed to release sync lock for {}: {}", provider, e);
+    }
+
+    state
+        .metrics
+        .record_sync(provider, started.elapsed(), result.is_ok());
+
+    let recorded = match &result {
+        Ok(notifications) => run.succeed(state, notifications).await,
+        Err(e) => run.fail(state, &e.to_string()).await,
+    };
+    if let Err(e) = recorded {
+        error!("Failed to record job run {}: {}", run.id, e);
+    }
+
+    let notifications = result.inspect_err(|e| error!("Sync for {} failed: {}", provider, e))?;
+
+    state.channels.send(state, &notifications).await;
+
+    Ok(notifications)
+}
+
+fn reject_if_shutting_down(state: &AppState) -> Result<(), Error> {
+    if state.shutdown.is_requested() {
+        return Err(Error::from_string(
+            "Shutting down",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(ApiResponse)]
+enum SyncStartedResponse {
+    /// The sync was started, or was already running for this provider
+    #[oai(status = 202)]
+    Accepted(Json<SyncJob>),
+}
+
+#[derive(ApiResponse)]
+enum SyncAllStartedResponse {
+    /// One job per configured provider
+    #[oai(status = 202)]
+    Accepted(Json<Vec<SyncJob>>),
+}
+
+pub struct SyncApi;
+
+#[OpenApi]
+impl SyncApi {
+    /// Sync all providers
+    ///
+    /// Starts a background sync for every configured provider.
+    /// Providers that are already syncing return their running job.
+    #[oai(path = "/sync", method = "post", tag = "ApiTags::Sync")]
+    async fn sync_all(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+    ) -> Result<SyncAllStartedResponse, Error> {
+        auth.require(Scope::WriteDomains)?;
+        reject_if_shutting_down(&state)?;
+
+        let jobs = PROVIDERS
+            .iter()
+            .filter(|provider| is_configured(&state, provider))
+            .map(|provider| state.sync_jobs.start(&state, provider))
+            .collect();
+
+        Ok(SyncAllStartedResponse::Accepted(Json(jobs)))
+    }
+
+    /// Sync a single provider
+    #[oai(path = "/sync/:provider", method = "post", tag = "ApiTags::Sync")]
+    async fn sync_provider(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        provider: Path<String>,
+    ) -> Result<SyncStartedResponse, Error> {
+        auth.require(Scope::WriteDomains)?;
+        reject_if_shutting_down(&state)?;
+
+        if !is_configured(&state, &provider) {
+            return Err(Error::from_string(
+                format!("Provider {} is not configured", provider.0),
+                StatusCode::NOT_FOUND,
+            ));
+        }
+
+        Ok(SyncStartedResponse::Accepted(Json(
+            state.sync_jobs.start(&state, &provider),
+        )))
+    }
+
+    /// Get the status of a sync job
+    #[oai(path = "/sync/:id", method = "get", tag = "ApiTags::Sync")]
+    async fn get_sync(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        id: Path<u64>,
+    ) -> Result<Json<SyncJob>, Error> {
+        auth.require(Scope::ReadDomains)?;
+
+        state
+            .sync_jobs
+            .get(id.0)
+            .map(Json)
+            .ok_or_else(|| Error::from_status(StatusCode::NOT_FOUND))
+    }
+
+    /// List job runs
+    ///
+    /// Scheduled, api and cli runs, most recent first.
+    #[oai(path = "/jobs", method = "get", tag = "ApiTags::Sync")]
+    async fn get_jobs(
+        &self,
+        state: Data<&AppState>,
+        auth: ApiAuth,
+        /// Only runs of this job ("domains", "dns", "pricing")
+        kind: Query<Option<String>>,
+        /// Only runs for this provider
+        provider: Query<Option<String>>,
+        /// Only runs with this outcome ("running", "success", "failure", "skipped")
+        outcome: Query<Option<String>>,
+        /// Number of runs (default 50, max 1000)
+        limit: Query<Option<u32>>,
+    ) -> Result<Json<Vec<JobRun>>, Error> {
+        auth.require(Scope::ReadDomains)?;
+
+        let filter = JobRunFilter {
+            kind: kind.0,
+            provider: provider.0,
+            outcome: outcome.0,
+        };
+        let limit = limit.0.unwrap_or(DEFAULT_JOBS_LIMIT).clamp(1, MAX_JOBS_LIMIT);
+
+        let runs = JobRun::find_recent(&state, &filter, limit)
+            .await
+            .map_err(|_| Error::from_status(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+        Ok(Json(runs))
+    }
+}
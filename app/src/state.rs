@@ -1,7 +1,14 @@
 use crate::{
     cache::AppCache,
     database::Database,
-    modules::{cloudflare::CloudflareService, ntfy::NtfyService, porkbun::PorkbunService},
+    modules::{
+        channels::Channels, cloudflare::CloudflareService, porkbun::PorkbunService,
+        reminders::ReminderConfig,
+    },
+    server::{
+        cal::CalendarConfig, metrics::Metrics, notifications::NotificationBus, rss::RssConfig,
+        schedule::ScheduleConfig, shutdown::Shutdown, sync::SyncJobs, HttpConfig,
+    },
 };
 use async_std::path::Path;
 use dirs;
@@ -11,7 +18,7 @@ use figment::{
 };
 use serde::{Deserialize, Serialize};
 use std::{path::PathBuf, sync::Arc};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 pub type AppState = Arc<AppStateInner>;
 
@@ -28,17 +35,23 @@ pub struct ServerConfig {
 pub struct AppStateInner {
     pub database: Database,
     pub api: Option<ServerConfig>,
+    pub http: HttpConfig,
+    pub calendar: CalendarConfig,
+    pub rss: RssConfig,
+    pub schedule: ScheduleConfig,
+    pub reminders: ReminderConfig,
     pub cache: AppCache,
+    pub metrics: Metrics,
+    pub notification_bus: NotificationBus,
+    pub sync_jobs: SyncJobs,
+    pub shutdown: Shutdown,
     pub porkbun: Option<PorkbunService>,
     pub cloudflare: Option<CloudflareService>,
-    pub ntfy: Option<NtfyService>,
+    pub channels: Channels,
 }
 
-fn get_config_file(server: bool) -> Option<Figment> {
-    if !server {
-        return None;
-    }
-
+/// The config file, also loaded by the cli so syncs it runs notify the configured channels
+pub fn get_config_file() -> Option<Figment> {
     let config_file = std::env::var("DMN_CONFIG_PATH").map(|path| PathBuf::from(&path)).unwrap_or_else(|_| {
         let config_dir = dirs::config_dir().unwrap();
         let config_dmn_dir = config_dir.join("dmn");
@@ -79,7 +92,7 @@ fn get_config_file(server: bool) -> Option<Figment> {
 impl AppStateInner {
     pub async fn init(server: bool) -> Self {
         // Load configuration from environment variables
-        let config_file = match get_config_file(server) {
+        let config_file = match get_config_file() {
             Some(config_file) => config_file,
             None => {
                 error!("Failed to load config file");
@@ -115,7 +128,64 @@ impl AppStateInner {
             }
         };
 
+        let http = Figment::new()
+            .merge(Env::prefixed("DMN_SERVER_"))
+            .merge(config_file.focus("server"))
+            .extract::<HttpConfig>()
+            .unwrap_or_else(|error| {
+                warn!("Failed to load server config: {}", error);
+                HttpConfig::default()
+            });
+
+        let calendar = Figment::new()
+            .merge(Env::prefixed("DMN_CALENDAR_"))
+            .merge(config_file.focus("calendar"))
+            .extract::<CalendarConfig>()
+            .unwrap_or_else(|error| {
+                warn!("Failed to load calendar config: {}", error);
+                CalendarConfig::default()
+            });
+
+        if server && calendar.secret.is_none() {
+            warn!("No calendar secret configured, the calendar feed will not be served");
+        }
+
+        let rss = Figment::new()
+            .merge(Env::prefixed("DMN_RSS_"))
+            .merge(config_file.focus("rss"))
+            .extract::<RssConfig>()
+            .unwrap_or_else(|error| {
+                warn!("Failed to load rss config: {}", error);
+                RssConfig::default()
+            });
+
+        if server && rss.secret.is_none() {
+            warn!("No rss secret configured, the rss feeds will not be served");
+        }
+
+        let schedule = Figment::new()
+            .merge(Env::prefixed("DMN_SCHEDULE_"))
+            .merge(config_file.focus("schedule"))
+            .extract::<ScheduleConfig>()
+            .unwrap_or_else(|error| {
+                warn!("Failed to load schedule config: {}", error);
+                ScheduleConfig::default()
+            });
+
+        let reminders = Figment::new()
+            .merge(Env::prefixed("DMN_REMINDERS_"))
+            .merge(config_file.focus("reminders"))
+            .extract::<ReminderConfig>()
+            .unwrap_or_else(|error| {
+                warn!("Failed to load reminders config: {}", error);
+                ReminderConfig::default()
+            });
+
         let cache = AppCache::new();
+        let metrics = Metrics::new();
+        let notification_bus = NotificationBus::new();
+        let sync_jobs = SyncJobs::new();
+        let shutdown = Shutdown::new();
 
         let porkbun = if server {
             PorkbunService::try_init(&config_file).await
@@ -128,19 +198,62 @@ impl AppStateInner {
             None
         };
 
-        let ntfy = NtfyService::try_init(&config_file).await;
+        let channels = Channels::load(&config_file).await;
 
         Self {
             database,
             cache,
+            metrics,
+            notification_bus,
+            sync_jobs,
+            shutdown,
             api,
+            http,
+            calendar,
+            rss,
+            schedule,
+            reminders,
             porkbun,
             cloudflare,
-            ntfy,
+            channels,
         }
     }
 }
 
+#[cfg(test)]
+impl AppStateInner {
+    /// State on a fresh database without config, providers or channels
+    pub async fn test() -> AppState {
+        let path = std::env::temp_dir().join(format!(
+            "dmn-test-{}-{}.sqlite",
+            std::process::id(),
+            rand::random::<u64>()
+        ));
+        let database = Database::init(&DatabaseConfig {
+            url: Some(format!("sqlite:{}", path.display())),
+        })
+        .await;
+
+        Arc::new(Self {
+            database,
+            api: None,
+            http: HttpConfig::default(),
+            calendar: CalendarConfig::default(),
+            rss: RssConfig::default(),
+            schedule: ScheduleConfig::default(),
+            reminders: ReminderConfig::default(),
+            cache: AppCache::new(),
+            metrics: Metrics::new(),
+            notification_bus: NotificationBus::new(),
+            sync_jobs: SyncJobs::new(),
+            shutdown: Shutdown::new(),
+            porkbun: None,
+            cloudflare: None,
+            channels: Channels::default(),
+        })
+    }
+}
+
 impl std::fmt::Debug for AppStateInner {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AppStateInner").finish()
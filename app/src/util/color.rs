@@ -1,17 +1,32 @@
 use colored::Colorize;
 
 pub fn colorize_provider(provider: &str) -> String {
+    match provider_rgb(provider) {
+        Some((r, g, b)) => provider_name(provider).truecolor(r, g, b).to_string(),
+        None => provider.to_string(),
+    }
+}
+
+pub fn provider_name(provider: &str) -> &str {
+    match provider {
+        "cloudflare" => "Cloudflare",
+        "porkbun" => "Porkbun",
+        _ => provider,
+    }
+}
+
+/// Brand colour of a provider, used by the cli and the notification channels
+pub fn provider_rgb(provider: &str) -> Option<(u8, u8, u8)> {
     match provider {
-        "cloudflare" => "Cloudflare"
-            .to_string()
-            .green()
-            .truecolor(244, 129, 32)
-            .to_string(),
-        "porkbun" => "Porkbun"
-            .to_string()
-            .green()
-            .truecolor(239, 120, 120)
-            .to_string(),
-        _ => provider.to_string(),
+        "cloudflare" => Some((244, 129, 32)),
+        "porkbun" => Some((239, 120, 120)),
+        _ => None,
     }
 }
+
+/// Brand colour of a provider as `#rrggbb`, gray for unknown providers
+pub fn provider_hex(provider: &str) -> String {
+    let (r, g, b) = provider_rgb(provider).unwrap_or((107, 114, 128));
+
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
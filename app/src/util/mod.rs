@@ -1,2 +1,5 @@
 pub mod serde_strint;
 pub mod color;
+pub mod value;
+#[cfg(test)]
+pub mod stub;
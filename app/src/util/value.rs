@@ -0,0 +1,12 @@
+/// Loose truthiness of provider values, `true`, `1` & `"true"`
+pub fn bool_from_value(value: &serde_json::Value) -> bool {
+    if value.is_boolean() {
+        value.as_bool().unwrap()
+    } else if value.is_number() {
+        value.as_i64().unwrap() != 0
+    } else if value.is_string() {
+        value.as_str().unwrap().to_lowercase() == "true"
+    } else {
+        false
+    }
+}
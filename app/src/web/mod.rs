@@ -1,9 +1,13 @@
+use crate::models::api_token::Scope;
 use crate::models::domain::Domain;
+use crate::server::auth;
 use crate::state::AppState;
 use chrono::{DateTime, Duration, Utc};
 use chrono_humanize::HumanTime;
 use maud::{html, Markup, DOCTYPE};
+use poem::http::{header::WWW_AUTHENTICATE, StatusCode};
 use poem::web::Data;
+use poem::{IntoResponse, Request, Response};
 
 pub fn provider_to_color(provider: &String) -> &str {
     match provider.as_str() {
@@ -48,13 +52,35 @@ fn format_cloudflare_url(account_id: &str, domain: &str) -> String {
 }
 
 #[poem::handler]
-pub async fn web_endpoint(state: Data<&AppState>) -> poem_openapi::payload::Html<String> {
+pub async fn web_endpoint(state: Data<&AppState>, req: &Request) -> Response {
+    let principal = match auth::bearer_token(req) {
+        Some(token) => auth::authenticate(&state, token).await,
+        None => None,
+    };
+
+    match principal {
+        Some(principal) if principal.has_scope(Scope::ReadDomains) => {}
+        Some(_) => return Response::builder().status(StatusCode::FORBIDDEN).finish(),
+        None => {
+            return Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header(WWW_AUTHENTICATE, "Bearer")
+                .finish()
+        }
+    }
+
     let domains = match Domain::get_all(&state).await {
         Ok(domains) => domains,
         Err(_) => vec![],
     };
 
     let total_domains = domains.len();
+    let base_url = state.http.base_url().unwrap_or_default();
+
+    // the feed secrets are never rendered, subscribers append `?token=` themselves
+    let rss_enabled = state.rss.enabled.unwrap_or(true) && state.rss.secret.is_some();
+    let calendar_enabled =
+        state.calendar.enabled.unwrap_or(true) && state.calendar.secret.is_some();
 
     let (already_expired_domains, active_domains): (Vec<Domain>, Vec<Domain>) = domains
         .into_iter()
@@ -74,9 +100,13 @@ pub async fn web_endpoint(state: Data<&AppState>) -> poem_openapi::payload::Html
                     div class="flex gap-4 justify-between items-baseline px-4 w-full" {
                         h1 class="text-2xl font-bold" { "Domains" }
                         div class="flex gap-4 items-baseline" {
-                            a href="/api/rss.xml" class="text-blue-500 hover:underline" target="_blank" { "Rss" }
-                            a href="/api/domains.ics" class="text-blue-500 hover:underline" { "Calendar" }
-                            a href="/docs" class="text-blue-500 hover:underline" target="_blank" { "Docs" }
+                            @if rss_enabled {
+                                a href={(base_url) "/api/rss.xml"} class="text-blue-500 hover:underline" target="_blank" { "Rss" }
+                            }
+                            @if calendar_enabled {
+                                a href={(base_url) "/api/domains.ics"} class="text-blue-500 hover:underline" { "Calendar" }
+                            }
+                            a href={(base_url) "/docs"} class="text-blue-500 hover:underline" target="_blank" { "Docs" }
                         }
                     }
                     div class="grid grid-cols-4 gap-4" {
@@ -118,7 +148,7 @@ pub async fn web_endpoint(state: Data<&AppState>) -> poem_openapi::payload::Html
         }
     };
 
-    poem_openapi::payload::Html(markup.into_string())
+    poem_openapi::payload::Html(markup.into_string()).into_response()
 }
 
 fn domain_table(domains: Vec<Domain>) -> Markup {